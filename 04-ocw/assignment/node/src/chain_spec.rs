@@ -1,12 +1,19 @@
 use node_template_runtime::{
-	AccountId, AuraConfig, BalancesConfig, GenesisConfig, GrandpaConfig, Signature, SudoConfig,
-	SystemConfig, WASM_BINARY,
+	pallet_ocw::{
+		commit::SubmissionMode,
+		crypto::Public as OcwId,
+		feed::FeedConfig,
+		PriceGuard, GITHUB_FEED_ID,
+	},
+	AccountId, AuraConfig, BalancesConfig, GenesisConfig, GrandpaConfig, OcwDemo, OcwDemoConfig,
+	Permill, Signature, SudoConfig, SystemConfig, WASM_BINARY,
 };
 use sc_service::ChainType;
 use sp_consensus_aura::sr25519::AuthorityId as AuraId;
 use sp_core::{sr25519, Pair, Public};
 use sp_finality_grandpa::AuthorityId as GrandpaId;
 use sp_runtime::traits::{IdentifyAccount, Verify};
+use std::convert::TryInto;
 
 // The URL for the telemetry server.
 // const STAGING_TELEMETRY_URL: &str = "wss://telemetry.polkadot.io/submit/";
@@ -31,9 +38,36 @@ where
 	AccountPublic::from(get_from_seed::<TPublic>(seed)).into_account()
 }
 
-/// Generate an Aura authority key.
-pub fn authority_keys_from_seed(s: &str) -> (AuraId, GrandpaId) {
-	(get_from_seed::<AuraId>(s), get_from_seed::<GrandpaId>(s))
+/// A feed reading the USD price of `coincap_id` from coincap.
+fn coincap_feed(coincap_id: &str) -> FeedConfig {
+	let url = format!("https://api.coincap.io/v2/assets/{}", coincap_id);
+	FeedConfig::http_get(url.as_bytes(), b"/data/priceUsd", 0).expect("coincap feeds are bounded")
+}
+
+/// The feed the offchain worker fetches the GitHub organisation info from.
+fn github_feed() -> FeedConfig {
+	let mut feed =
+		FeedConfig::http_get(b"https://api.github.com/orgs/substrate-developer-hub", b"", 0)
+			.expect("the GitHub feed is bounded");
+	// GitHub rejects requests without a user agent.
+	let user_agent = (
+		b"User-Agent".to_vec().try_into().expect("header names are bounded"),
+		b"jimmychu0807".to_vec().try_into().expect("header values are bounded"),
+	);
+	feed.headers.try_push(user_agent).expect("a feed has room for a header");
+	feed
+}
+
+/// Generate the session keys of an authority: Aura, Grandpa and the `demo` key its offchain
+/// worker signs with.
+pub fn authority_keys_from_seed(s: &str) -> (AuraId, GrandpaId, OcwId) {
+	(get_from_seed::<AuraId>(s), get_from_seed::<GrandpaId>(s), get_from_seed::<OcwId>(s))
+}
+
+/// The account of an offchain worker key.
+fn ocw_account(key: &OcwId) -> AccountId {
+	let public: &sr25519::Public = key.as_ref();
+	AccountPublic::from(*public).into_account()
 }
 
 pub fn development_config() -> Result<ChainSpec, String> {
@@ -125,7 +159,7 @@ pub fn local_testnet_config() -> Result<ChainSpec, String> {
 /// Configure initial storage state for FRAME modules.
 fn testnet_genesis(
 	wasm_binary: &[u8],
-	initial_authorities: Vec<(AuraId, GrandpaId)>,
+	initial_authorities: Vec<(AuraId, GrandpaId, OcwId)>,
 	root_key: AccountId,
 	endowed_accounts: Vec<AccountId>,
 	_enable_println: bool,
@@ -137,8 +171,14 @@ fn testnet_genesis(
 			changes_trie_config: Default::default(),
 		},
 		balances: BalancesConfig {
-			// Configure endowed accounts with initial balance of 1 << 60.
-			balances: endowed_accounts.iter().cloned().map(|k| (k, 1 << 60)).collect(),
+			// Configure endowed accounts, and the pot paying the oracle rewards, with initial
+			// balance of 1 << 60.
+			balances: endowed_accounts
+				.iter()
+				.cloned()
+				.chain(std::iter::once(OcwDemo::pot_account()))
+				.map(|k| (k, 1 << 60))
+				.collect(),
 		},
 		aura: AuraConfig {
 			authorities: initial_authorities.iter().map(|x| (x.0.clone())).collect(),
@@ -150,5 +190,47 @@ fn testnet_genesis(
 			// Assign network admin rights.
 			key: root_key,
 		},
+		ocw_demo: OcwDemoConfig {
+			// The offchain worker of every authority reports prices.
+			oracles: initial_authorities.iter().map(|x| ocw_account(&x.2)).collect(),
+			feeds: vec![
+				(b"polkadot".to_vec(), coincap_feed("polkadot")),
+				(b"kusama".to_vec(), coincap_feed("kusama")),
+				(
+					GITHUB_FEED_ID.to_vec(),
+					github_feed(),
+				),
+			],
+			assets: vec![
+				(b"DOT".to_vec(), vec![b"polkadot".to_vec()]),
+				(b"KSM".to_vec(), vec![b"kusama".to_vec()]),
+			],
+			// Prices are left to the oracles, so the first one is not checked against a seeded
+			// one.
+			prices: vec![],
+			// A price older than 10 minutes is stale; one moving 20% trips the circuit breaker.
+			price_guards: vec![(
+				b"DOT".to_vec(),
+				PriceGuard { max_age: 10 * 60_000, max_jump: Permill::from_percent(20) },
+			)],
+			submission_mode: SubmissionMode::Direct,
+			// Every offchain worker task follows its default schedule.
+			task_schedules: vec![],
+		},
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn authorities_are_the_genesis_oracles() {
+		let authorities = vec![authority_keys_from_seed("Alice"), authority_keys_from_seed("Bob")];
+		let alice = get_account_id_from_seed::<sr25519::Public>("Alice");
+		let genesis = testnet_genesis(&[], authorities, alice.clone(), vec![], true);
+		let bob = get_account_id_from_seed::<sr25519::Public>("Bob");
+		// The `//Alice` key a development node inserts signs for an oracle.
+		assert_eq!(genesis.ocw_demo.oracles, vec![alice, bob]);
 	}
 }
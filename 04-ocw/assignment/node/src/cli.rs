@@ -1,4 +1,9 @@
+use crate::service::OcwKeys;
 use sc_cli::RunCmd;
+use std::{
+	fs, io,
+	path::{Path, PathBuf},
+};
 use structopt::StructOpt;
 
 #[derive(Debug, StructOpt)]
@@ -8,6 +13,84 @@ pub struct Cli {
 
 	#[structopt(flatten)]
 	pub run: RunCmd,
+
+	#[structopt(flatten)]
+	pub ocw: OcwKeyParams,
+}
+
+/// Keys the offchain worker signs with, inserted into the keystore at startup.
+#[derive(Debug, Clone, StructOpt)]
+pub struct OcwKeyParams {
+	/// Secret URI of an sr25519 key for the offchain worker, e.g. a mnemonic. May be repeated.
+	///
+	/// Dev seeds such as `//Alice` are only accepted on development chains.
+	#[structopt(long = "ocw-key-uri", value_name = "SURI")]
+	pub key_uris: Vec<String>,
+
+	/// File holding the secret URI of an sr25519 key for the offchain worker. May be repeated.
+	#[structopt(long = "ocw-key-file", value_name = "PATH", parse(from_os_str))]
+	pub key_files: Vec<PathBuf>,
+
+	/// Do not insert the `//Alice` key development chains otherwise get when no key is given.
+	#[structopt(long = "no-dev-ocw-key")]
+	pub no_dev_key: bool,
+}
+
+impl OcwKeyParams {
+	/// The keys to insert, reading the key files.
+	pub fn ocw_keys(&self) -> sc_cli::Result<OcwKeys> {
+		self.resolve(|path| fs::read_to_string(path)).map_err(Into::into)
+	}
+
+	/// The keys to insert, reading the key files with `read`. The dev key is only asked for if
+	/// no other key is given.
+	fn resolve(&self, read: impl Fn(&Path) -> io::Result<String>) -> Result<OcwKeys, String> {
+		let mut secret_uris = self.key_uris.clone();
+		for path in self.key_files.iter() {
+			let suri = read(path).map_err(|e| {
+				format!("Could not read the ocw key file {}: {}", path.display(), e)
+			})?;
+			secret_uris.push(suri.trim().to_string());
+		}
+		let dev_key = !self.no_dev_key && secret_uris.is_empty();
+		Ok(OcwKeys { secret_uris, dev_key })
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn params(args: &[&str]) -> OcwKeyParams {
+		OcwKeyParams::from_iter_safe(std::iter::once("node").chain(args.iter().copied())).unwrap()
+	}
+
+	fn read(path: &Path) -> io::Result<String> {
+		match path.to_str() {
+			Some("key") => Ok("  caution juice atom organ\n".into()),
+			_ => Err(io::ErrorKind::NotFound.into()),
+		}
+	}
+
+	#[test]
+	fn key_uris_come_before_trimmed_key_files() {
+		let params = params(&["--ocw-key-file", "key", "--ocw-key-uri", "//Bob"]);
+		let keys = params.resolve(read).unwrap();
+		assert_eq!(keys.secret_uris, vec!["//Bob", "caution juice atom organ"]);
+		assert!(!keys.dev_key);
+	}
+
+	#[test]
+	fn dev_key_is_only_asked_for_without_other_keys() {
+		assert!(params(&[]).resolve(read).unwrap().dev_key);
+		assert!(!params(&["--no-dev-ocw-key"]).resolve(read).unwrap().dev_key);
+	}
+
+	#[test]
+	fn unreadable_key_files_are_reported() {
+		let error = params(&["--ocw-key-file", "missing"]).resolve(read).unwrap_err();
+		assert!(error.starts_with("Could not read the ocw key file missing"));
+	}
 }
 
 #[derive(Debug, StructOpt)]
@@ -38,4 +121,7 @@ pub enum Subcommand {
 	/// The custom benchmark subcommmand benchmarking runtime pallets.
 	#[structopt(name = "benchmark", about = "Benchmark runtime pallets.")]
 	Benchmark(frame_benchmarking_cli::BenchmarkCmd),
+
+	/// Serve coincap- and GitHub-compatible JSON on localhost, to run offchain workers offline.
+	MockFeeds(crate::mock_feeds::MockFeedsCmd),
 }
@@ -115,6 +115,7 @@ pub fn run() -> sc_cli::Result<()> {
 				Ok((cmd.run(client, backend), task_manager))
 			})
 		},
+		Some(Subcommand::MockFeeds(cmd)) => cmd.run(),
 		Some(Subcommand::Benchmark(cmd)) =>
 			if cfg!(feature = "runtime-benchmarks") {
 				let runner = cli.create_runner(cmd)?;
@@ -126,11 +127,12 @@ pub fn run() -> sc_cli::Result<()> {
 					.into())
 			},
 		None => {
+			let ocw_keys = cli.ocw.ocw_keys()?;
 			let runner = cli.create_runner(&cli.run)?;
 			runner.run_node_until_exit(|config| async move {
 				match config.role {
 					Role::Light => service::new_light(config),
-					_ => service::new_full(config),
+					_ => service::new_full(config, ocw_keys),
 				}
 				.map_err(sc_cli::Error::Service)
 			})
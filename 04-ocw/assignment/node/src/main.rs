@@ -6,6 +6,7 @@ mod chain_spec;
 mod service;
 mod cli;
 mod command;
+mod mock_feeds;
 mod rpc;
 
 fn main() -> sc_cli::Result<()> {
@@ -0,0 +1,414 @@
+//! `mock-feeds`: a local stand-in for the coincap and GitHub APIs the offchain worker queries.
+//!
+//! Point the feeds at it, e.g. `http://127.0.0.1:8089/v2/assets/polkadot` with the JSON pointer
+//! `/data/priceUsd`, or `http://127.0.0.1:8089/orgs/substrate-developer-hub` for `github`, to run
+//! the whole offchain worker pipeline without network access.
+
+use std::{
+	collections::HashMap,
+	fs,
+	io::{self, BufRead, BufReader, Write},
+	net::{TcpListener, TcpStream},
+	path::{Path, PathBuf},
+	sync::{Arc, Condvar, Mutex},
+	thread,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use structopt::StructOpt;
+
+/// Maximum number of connections served at once. Further ones wait to be accepted.
+const MAX_CONNECTIONS: usize = 16;
+
+/// How long a connection may take to send its request, or to take the response.
+const IO_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Lowest price of a random walk: the smallest one the responses quote, with 8 decimals. A walk
+/// can always move up from it.
+const MIN_PRICE: f64 = 1e-8;
+
+/// Serve coincap- and GitHub-compatible JSON on localhost.
+#[derive(Debug, Clone, StructOpt)]
+pub struct MockFeedsCmd {
+	/// Port to listen on, on 127.0.0.1.
+	#[structopt(long, default_value = "8089")]
+	pub port: u16,
+
+	/// Replay prices from a CSV file of `asset_id,price_usd` rows instead of a random walk.
+	/// Every request for an asset returns its next price, starting over after the last one.
+	#[structopt(long, value_name = "CSV", parse(from_os_str))]
+	pub replay: Option<PathBuf>,
+
+	/// Price every asset starts its random walk at, in USD.
+	#[structopt(long, default_value = "20")]
+	pub start_price: f64,
+
+	/// Largest relative move of a random walk step, e.g. `0.02` for 2%.
+	#[structopt(long, default_value = "0.01")]
+	pub volatility: f64,
+
+	/// Seed of the random walk and of the injected faults. Defaults to the current time.
+	#[structopt(long)]
+	pub seed: Option<u64>,
+
+	/// Percentage of requests answered with `--error-status` instead of a price.
+	#[structopt(long, default_value = "0")]
+	pub error_rate: u8,
+
+	/// HTTP status of the injected errors.
+	#[structopt(long, default_value = "503")]
+	pub error_status: u16,
+
+	/// Percentage of requests answered with malformed JSON.
+	#[structopt(long, default_value = "0")]
+	pub malformed_rate: u8,
+
+	/// Milliseconds every response is delayed by.
+	#[structopt(long, default_value = "0")]
+	pub latency: u64,
+
+	/// Up to this many milliseconds are randomly added to `--latency`.
+	#[structopt(long, default_value = "0")]
+	pub jitter: u64,
+}
+
+/// Where the prices of an asset come from.
+enum PriceSource {
+	RandomWalk(f64),
+	Replay { prices: Vec<f64>, next: usize },
+}
+
+impl PriceSource {
+	fn next(&mut self, rng: &mut Rng, volatility: f64) -> f64 {
+		match self {
+			PriceSource::RandomWalk(price) => {
+				let step = (rng.unit() * 2.0 - 1.0) * volatility;
+				*price = (*price * (1.0 + step)).max(MIN_PRICE);
+				*price
+			},
+			PriceSource::Replay { prices, next } => {
+				let price = prices[*next];
+				*next = (*next + 1) % prices.len();
+				price
+			},
+		}
+	}
+}
+
+/// xorshift64*, enough to script prices and faults reproducibly.
+struct Rng(u64);
+
+impl Rng {
+	fn new(seed: u64) -> Self {
+		// xorshift 的状态不能为 0。
+		Rng(seed | 1)
+	}
+
+	fn next_u64(&mut self) -> u64 {
+		self.0 ^= self.0 >> 12;
+		self.0 ^= self.0 << 25;
+		self.0 ^= self.0 >> 27;
+		self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+	}
+
+	/// A number in `[0, 1)`.
+	fn unit(&mut self) -> f64 {
+		(self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+	}
+
+	/// Whether an event happening `percent`% of the time happens.
+	fn chance(&mut self, percent: u8) -> bool {
+		self.next_u64() % 100 < percent as u64
+	}
+}
+
+struct State {
+	rng: Rng,
+	/// Prices of the assets requested so far or replayed, by coincap id.
+	assets: HashMap<String, PriceSource>,
+	/// Whether unknown assets start a random walk, i.e. nothing is replayed.
+	random_walk: bool,
+}
+
+/// Connections being served, at most `MAX_CONNECTIONS`.
+#[derive(Default)]
+struct Connections {
+	count: Mutex<usize>,
+	freed: Condvar,
+}
+
+impl Connections {
+	/// Wait for a free slot and take it until the returned guard is dropped.
+	fn acquire(self: &Arc<Self>) -> ConnectionSlot {
+		let mut count = self.count.lock().expect("no thread panics holding the count; qed");
+		while *count >= MAX_CONNECTIONS {
+			count = self.freed.wait(count).expect("no thread panics holding the count; qed");
+		}
+		*count += 1;
+		ConnectionSlot(self.clone())
+	}
+}
+
+/// A slot of `Connections`, freed when dropped.
+struct ConnectionSlot(Arc<Connections>);
+
+impl Drop for ConnectionSlot {
+	fn drop(&mut self) {
+		*self.0.count.lock().expect("no thread panics holding the count; qed") -= 1;
+		self.0.freed.notify_one();
+	}
+}
+
+/// A response to send back.
+struct Response {
+	status: u16,
+	body: String,
+}
+
+impl MockFeedsCmd {
+	/// Serve requests until the process is stopped.
+	pub fn run(&self) -> sc_cli::Result<()> {
+		if self.error_rate > 100 || self.malformed_rate > 100 {
+			return Err("`--error-rate` and `--malformed-rate` are percentages".into())
+		}
+		if !(0.0..1.0).contains(&self.volatility) {
+			return Err("`--volatility` is a fraction below 1".into())
+		}
+		sc_tracing::logging::LoggerBuilder::new("")
+			.init()
+			.map_err(|e| format!("Could not set up logging: {}", e))?;
+		let seed = self.seed.unwrap_or_else(|| {
+			SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64)
+		});
+		let assets = match &self.replay {
+			Some(path) => load_replay(path)?,
+			None => HashMap::new(),
+		};
+		let state = Arc::new(Mutex::new(State {
+			rng: Rng::new(seed),
+			assets,
+			random_walk: self.replay.is_none(),
+		}));
+
+		let listener = TcpListener::bind(("127.0.0.1", self.port))?;
+		log::info!("Serving mock feeds on http://127.0.0.1:{} (seed {})", self.port, seed);
+		let connections = Arc::new(Connections::default());
+		loop {
+			let slot = connections.acquire();
+			let stream = match listener.accept() {
+				Ok((stream, _)) => stream,
+				Err(e) => {
+					log::warn!("Failed to accept a connection: {}", e);
+					continue
+				},
+			};
+			let (cmd, state) = (self.clone(), state.clone());
+			thread::spawn(move || {
+				let _slot = slot;
+				if let Err(e) = cmd.serve(stream, &state) {
+					log::warn!("Failed to serve a request: {}", e);
+				}
+			});
+		}
+	}
+
+	fn serve(&self, stream: TcpStream, state: &Mutex<State>) -> io::Result<()> {
+		stream.set_read_timeout(Some(IO_TIMEOUT))?;
+		stream.set_write_timeout(Some(IO_TIMEOUT))?;
+		let mut reader = BufReader::new(stream.try_clone()?);
+		let mut request_line = String::new();
+		reader.read_line(&mut request_line)?;
+		// 忽略请求头；GET 和 POST 请求都没有需要读取的 body。
+		let mut header = String::new();
+		while reader.read_line(&mut header)? > 2 {
+			header.clear();
+		}
+
+		let path = request_line.split_whitespace().nth(1).unwrap_or("/").to_string();
+		let (response, delay) = {
+			let mut state = state.lock().expect("no thread panics holding the state; qed");
+			let delay = self.latency + (state.rng.unit() * self.jitter as f64) as u64;
+			(self.respond(&path, &mut state), delay)
+		};
+		thread::sleep(Duration::from_millis(delay));
+		log::info!("{} {}", response.status, path);
+		write_response(stream, response)
+	}
+
+	fn respond(&self, path: &str, state: &mut State) -> Response {
+		if state.rng.chance(self.error_rate) {
+			return Response { status: self.error_status, body: r#"{"error":"injected"}"#.into() }
+		}
+		if state.rng.chance(self.malformed_rate) {
+			return Response { status: 200, body: r#"{"data":{"priceUsd":"#.into() }
+		}
+
+		let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+		match segments.as_slice() {
+			["v2", "assets", id] => self.coincap_asset(id, state),
+			["orgs", org] => Response {
+				status: 200,
+				body: format!(
+					r#"{{"login":"{}","blog":"https://{}.example","public_repos":{}}}"#,
+					org,
+					org,
+					state.rng.next_u64() % 100
+				),
+			},
+			_ => not_found(),
+		}
+	}
+
+	fn coincap_asset(&self, id: &str, state: &mut State) -> Response {
+		if !state.assets.contains_key(id) {
+			if !state.random_walk {
+				return not_found()
+			}
+			state.assets.insert(id.to_string(), PriceSource::RandomWalk(self.start_price));
+		}
+		let State { rng, assets, .. } = state;
+		let price = assets.get_mut(id).expect("inserted above; qed").next(rng, self.volatility);
+		let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis());
+		Response {
+			status: 200,
+			body: format!(
+				r#"{{"data":{{"id":"{}","symbol":"{}","priceUsd":"{:.8}"}},"timestamp":{}}}"#,
+				id,
+				id.to_uppercase(),
+				price,
+				timestamp
+			),
+		}
+	}
+}
+
+fn not_found() -> Response {
+	Response { status: 404, body: r#"{"error":"not found"}"#.into() }
+}
+
+fn write_response(mut stream: TcpStream, response: Response) -> io::Result<()> {
+	let reason = match response.status {
+		200 => "OK",
+		404 => "Not Found",
+		429 => "Too Many Requests",
+		500 => "Internal Server Error",
+		503 => "Service Unavailable",
+		_ => "Mock Error",
+	};
+	write!(
+		stream,
+		"HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+		 Connection: close\r\n\r\n{}",
+		response.status,
+		reason,
+		response.body.len(),
+		response.body
+	)?;
+	stream.flush()
+}
+
+/// The prices of every asset in the CSV file at `path`, in file order.
+fn load_replay(path: &Path) -> Result<HashMap<String, PriceSource>, String> {
+	let csv = fs::read_to_string(path)
+		.map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+	let mut prices: HashMap<String, Vec<f64>> = HashMap::new();
+	for (n, line) in csv.lines().enumerate() {
+		let line = line.trim();
+		if line.is_empty() || line.starts_with('#') {
+			continue
+		}
+		let row = line.split(',').map(str::trim).collect::<Vec<_>>();
+		match row.as_slice() {
+			[id, price] => match price.parse::<f64>() {
+				Ok(price) if price >= 0.0 => prices.entry(id.to_string()).or_default().push(price),
+				// 第一行可以是表头。
+				_ if n == 0 => continue,
+				_ => return Err(format!("Invalid price on line {} of {}", n + 1, path.display())),
+			},
+			_ => return Err(format!("Expected `asset_id,price_usd` on line {}", n + 1)),
+		}
+	}
+	if prices.is_empty() {
+		return Err(format!("No prices to replay in {}", path.display()))
+	}
+	Ok(prices
+		.into_iter()
+		.map(|(id, prices)| (id, PriceSource::Replay { prices, next: 0 }))
+		.collect())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Load `csv` as a replay file.
+	fn replay(name: &str, csv: &str) -> Result<HashMap<String, PriceSource>, String> {
+		let file = format!("mock-feeds-{}-{}.csv", std::process::id(), name);
+		let path = std::env::temp_dir().join(file);
+		fs::write(&path, csv).unwrap();
+		let assets = load_replay(&path);
+		fs::remove_file(&path).unwrap();
+		assets
+	}
+
+	fn prices(source: &PriceSource) -> &[f64] {
+		match source {
+			PriceSource::Replay { prices, .. } => prices,
+			PriceSource::RandomWalk(_) => panic!("not a replay"),
+		}
+	}
+
+	#[test]
+	fn replay_files_group_prices_by_asset() {
+		let csv = "asset_id,price_usd\n# comment\n\npolkadot, 28.5\nkusama,301\npolkadot,29\n";
+		let assets = replay("valid", csv).unwrap();
+		assert_eq!(assets.len(), 2);
+		assert_eq!(prices(&assets["polkadot"]), &[28.5, 29.0]);
+		assert_eq!(prices(&assets["kusama"]), &[301.0]);
+	}
+
+	#[test]
+	fn invalid_replay_files_are_refused() {
+		assert!(replay("price", "polkadot,28.5\npolkadot,-1\n")
+			.unwrap_err()
+			.starts_with("Invalid price on line 2"));
+		assert_eq!(
+			replay("columns", "polkadot,28.5,1\n").unwrap_err(),
+			"Expected `asset_id,price_usd` on line 1"
+		);
+		assert!(replay("empty", "asset_id,price_usd\n").unwrap_err().starts_with("No prices"));
+		assert!(load_replay(Path::new("/nonexistent/prices.csv"))
+			.unwrap_err()
+			.starts_with("Could not read"));
+	}
+
+	#[test]
+	fn replays_start_over_after_the_last_price() {
+		let mut source = PriceSource::Replay { prices: vec![1.0, 2.0], next: 0 };
+		let mut rng = Rng::new(0);
+		let replayed: Vec<f64> = (0..5).map(|_| source.next(&mut rng, 0.5)).collect();
+		assert_eq!(replayed, vec![1.0, 2.0, 1.0, 2.0, 1.0]);
+	}
+
+	#[test]
+	fn rng_is_reproducible_and_in_range() {
+		let (mut a, mut b) = (Rng::new(42), Rng::new(42));
+		for _ in 0..1_000 {
+			assert_eq!(a.next_u64(), b.next_u64());
+			assert!((0.0..1.0).contains(&a.unit()));
+			assert!(!a.chance(0));
+			assert!(a.chance(100));
+			b.unit();
+		}
+		// A zero seed does not leave the state stuck at zero.
+		assert_ne!(Rng::new(0).next_u64(), 0);
+	}
+
+	#[test]
+	fn random_walks_stay_positive_and_move_off_the_floor() {
+		let mut rng = Rng::new(7);
+		let mut source = PriceSource::RandomWalk(0.0);
+		let walk: Vec<f64> = (0..100).map(|_| source.next(&mut rng, 0.01)).collect();
+		assert!(walk.iter().all(|price| *price >= MIN_PRICE));
+		assert!(walk.iter().any(|price| *price > MIN_PRICE));
+	}
+}
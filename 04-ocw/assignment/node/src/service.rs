@@ -7,10 +7,12 @@ use sc_executor::native_executor_instance;
 pub use sc_executor::NativeExecutor;
 use sc_finality_grandpa::SharedVoterState;
 use sc_keystore::LocalKeystore;
-use sc_service::{error::Error as ServiceError, Configuration, TaskManager};
+use sc_service::{error::Error as ServiceError, ChainType, Configuration, TaskManager};
 use sc_telemetry::{Telemetry, TelemetryWorker};
 use sp_consensus::SlotData;
 use sp_consensus_aura::sr25519::AuthorityPair as AuraPair;
+use sp_core::{crypto::DEV_PHRASE, Pair};
+use sp_keystore::{SyncCryptoStore, SyncCryptoStorePtr};
 use std::{sync::Arc, time::Duration};
 
 // Our native executor instance.
@@ -69,19 +71,6 @@ pub fn new_partial(
 		)?;
 	let client = Arc::new(client);
 
-	let keystore = keystore_container.sync_keystore();
-	if config.offchain_worker.enabled {
-		// Initialize seed for signing transaction using off-chain workers. This is a convenience
-		// so learners can see the transactions submitted simply running the node.
-		// Typically these keys should be inserted with RPC calls to `author_insertKey`.
-		sp_keystore::SyncCryptoStore::sr25519_generate_new(
-			&*keystore,
-			runtime::pallet_ocw::KEY_TYPE,
-			Some("//Alice"),
-		)
-		.expect("Creating key with account Alice should succeed.");
-	}
-
 	let telemetry = telemetry.map(|(worker, telemetry)| {
 		task_manager.spawn_handle().spawn("telemetry", worker.run());
 		telemetry
@@ -150,8 +139,74 @@ fn remote_keystore(_url: &String) -> Result<Arc<LocalKeystore>, &'static str> {
 	Err("Remote Keystore not supported.")
 }
 
+/// Keys the offchain worker signs with.
+#[derive(Debug, Clone, Default)]
+pub struct OcwKeys {
+	/// Secret URIs of sr25519 keys to insert into the keystore.
+	pub secret_uris: Vec<String>,
+	/// Whether to insert the `//Alice` key on development chains.
+	pub dev_key: bool,
+}
+
+/// Whether `suri` derives from the well-known development phrase, e.g. `//Alice` or `/Alice`.
+fn is_dev_seed(suri: &str) -> bool {
+	suri.starts_with('/') || suri.starts_with(DEV_PHRASE)
+}
+
+/// The secret URIs of the offchain worker keys to insert on a `chain_type` chain: the given
+/// ones, or `//Alice` on development chains if asked to. Dev seeds are refused unless the chain
+/// is a development chain, as anyone can sign with them.
+fn ocw_secret_uris<'a>(
+	chain_type: &ChainType,
+	keys: &'a OcwKeys,
+) -> Result<Vec<&'a str>, String> {
+	let development = *chain_type == ChainType::Development;
+	let dev_key = if keys.dev_key && development { Some("//Alice") } else { None };
+	let suris: Vec<&str> = keys.secret_uris.iter().map(String::as_str).chain(dev_key).collect();
+	if !development && suris.iter().any(|suri| is_dev_seed(suri)) {
+		return Err(format!(
+			"Refusing to insert a dev seed as ocw key on a {:?} chain.",
+			chain_type
+		))
+	}
+	Ok(suris)
+}
+
+/// Insert the keys of the offchain worker into `keystore`.
+///
+/// Every key must be an sr25519 key, the type of `pallet_ocw::KEY_TYPE`.
+fn insert_ocw_keys(
+	keystore: &SyncCryptoStorePtr,
+	chain_type: ChainType,
+	keys: &OcwKeys,
+) -> Result<(), ServiceError> {
+	for suri in ocw_secret_uris(&chain_type, keys).map_err(ServiceError::Other)? {
+		// 只接受 sr25519 密钥，错误信息中不回显私钥。
+		let pair = runtime::pallet_ocw::crypto::Pair::from_string(suri, None).map_err(|e| {
+			ServiceError::Other(format!("Invalid sr25519 ocw key: {:?}", e))
+		})?;
+		SyncCryptoStore::insert_unknown(
+			&**keystore,
+			runtime::pallet_ocw::KEY_TYPE,
+			suri,
+			pair.public().as_ref(),
+		)
+		.map_err(|_| ServiceError::Other("Could not insert the ocw key.".into()))?;
+	}
+	Ok(())
+}
+
 /// Builds a new service for a full client.
-pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError> {
+pub fn new_full(
+	mut config: Configuration,
+	ocw_keys: OcwKeys,
+) -> Result<TaskManager, ServiceError> {
+	// `pallet_ocw::request_indexing` hands its URLs to the offchain worker through offchain
+	// indexing, so a node running offchain workers needs it too.
+	if config.offchain_worker.enabled {
+		config.offchain_worker.indexing_enabled = true;
+	}
+
 	let sc_service::PartialComponents {
 		client,
 		backend,
@@ -174,6 +229,15 @@ pub fn new_full(mut config: Configuration) -> Result<TaskManager, ServiceError>
 		};
 	}
 
+	if config.offchain_worker.enabled {
+		// Keys can also be inserted later with RPC calls to `author_insertKey`.
+		insert_ocw_keys(
+			&keystore_container.sync_keystore(),
+			config.chain_spec.chain_type(),
+			&ocw_keys,
+		)?;
+	}
+
 	config.network.extra_sets.push(sc_finality_grandpa::grandpa_peers_set_config());
 
 	let (network, system_rpc_tx, network_starter) =
@@ -451,3 +515,43 @@ pub fn new_light(mut config: Configuration) -> Result<TaskManager, ServiceError>
 	network_starter.start_network();
 	Ok(task_manager)
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn keys(secret_uris: &[&str], dev_key: bool) -> OcwKeys {
+		OcwKeys { secret_uris: secret_uris.iter().map(|suri| suri.to_string()).collect(), dev_key }
+	}
+
+	#[test]
+	fn dev_seeds_are_recognised() {
+		assert!(is_dev_seed("//Alice"));
+		assert!(is_dev_seed("/Alice"));
+		assert!(is_dev_seed("//Alice///password"));
+		assert!(is_dev_seed(&format!("{}//Alice", DEV_PHRASE)));
+		assert!(!is_dev_seed("0x0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef"));
+		assert!(!is_dev_seed("caution juice atom organ advance problem want pledge someone"));
+	}
+
+	#[test]
+	fn development_chains_default_to_alice() {
+		let development = ChainType::Development;
+		assert_eq!(ocw_secret_uris(&development, &keys(&[], true)), Ok(vec!["//Alice"]));
+		assert_eq!(ocw_secret_uris(&development, &keys(&[], false)), Ok(vec![]));
+		assert_eq!(ocw_secret_uris(&development, &keys(&["//Bob"], false)), Ok(vec!["//Bob"]));
+	}
+
+	#[test]
+	fn dev_seeds_are_refused_outside_development_chains() {
+		let suri = "0x0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
+		for chain_type in [ChainType::Local, ChainType::Live].iter() {
+			// Not even `//Alice` when asked for.
+			assert_eq!(ocw_secret_uris(chain_type, &keys(&[], true)), Ok(vec![]));
+			assert_eq!(ocw_secret_uris(chain_type, &keys(&[suri], true)), Ok(vec![suri]));
+			assert!(ocw_secret_uris(chain_type, &keys(&[suri, "//Bob"], false)).is_err());
+			assert!(ocw_secret_uris(chain_type, &keys(&["/Bob"], false)).is_err());
+			assert!(ocw_secret_uris(chain_type, &keys(&[DEV_PHRASE], false)).is_err());
+		}
+	}
+}
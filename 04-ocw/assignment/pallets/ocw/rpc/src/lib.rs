@@ -0,0 +1,106 @@
+//! `ocw_*` JSON-RPC methods for querying the prices recorded by `pallet_ocw`.
+
+use std::sync::Arc;
+
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+pub use pallet_ocw_runtime_api::OcwApi as OcwRuntimeApi;
+use sp_api::ProvideRuntimeApi;
+use sp_arithmetic::per_things::Permill;
+use sp_blockchain::HeaderBackend;
+use sp_runtime::{generic::BlockId, traits::Block as BlockT};
+
+/// Error code returned when the runtime API call fails.
+const RUNTIME_ERROR: i64 = 1;
+
+#[rpc]
+pub trait OcwApi<BlockHash> {
+	/// The most recent price of `asset`, e.g. `"DOT"`.
+	#[rpc(name = "ocw_latestPrice")]
+	fn latest_price(&self, asset: String, at: Option<BlockHash>) -> Result<Option<(u64, Permill)>>;
+
+	/// The recorded prices of `asset`, oldest first.
+	#[rpc(name = "ocw_priceHistory")]
+	fn price_history(&self, asset: String, at: Option<BlockHash>) -> Result<Vec<(u64, Permill)>>;
+
+	/// Time-weighted average of the latest `window` prices of `asset`.
+	#[rpc(name = "ocw_twap")]
+	fn twap(
+		&self,
+		asset: String,
+		window: u32,
+		at: Option<BlockHash>,
+	) -> Result<Option<(u64, Permill)>>;
+}
+
+/// Implements [`OcwApi`] by calling into the runtime of `client`.
+pub struct Ocw<C, Block> {
+	client: Arc<C>,
+	_marker: std::marker::PhantomData<Block>,
+}
+
+impl<C, Block> Ocw<C, Block> {
+	/// Create a handler answering from the state of `client`.
+	pub fn new(client: Arc<C>) -> Self {
+		Self { client, _marker: Default::default() }
+	}
+}
+
+fn runtime_error_into_rpc_err(err: impl std::fmt::Debug) -> RpcError {
+	RpcError {
+		code: ErrorCode::ServerError(RUNTIME_ERROR),
+		message: "Runtime error".into(),
+		data: Some(format!("{:?}", err).into()),
+	}
+}
+
+impl<C, Block> Ocw<C, Block>
+where
+	Block: BlockT,
+	C: HeaderBackend<Block>,
+{
+	fn at(&self, at: Option<<Block as BlockT>::Hash>) -> BlockId<Block> {
+		BlockId::hash(at.unwrap_or_else(|| self.client.info().best_hash))
+	}
+}
+
+impl<C, Block> OcwApi<<Block as BlockT>::Hash> for Ocw<C, Block>
+where
+	Block: BlockT,
+	C: ProvideRuntimeApi<Block> + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: OcwRuntimeApi<Block>,
+{
+	fn latest_price(
+		&self,
+		asset: String,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Option<(u64, Permill)>> {
+		self.client
+			.runtime_api()
+			.latest_price(&self.at(at), asset.into_bytes())
+			.map_err(runtime_error_into_rpc_err)
+	}
+
+	fn price_history(
+		&self,
+		asset: String,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Vec<(u64, Permill)>> {
+		self.client
+			.runtime_api()
+			.price_history(&self.at(at), asset.into_bytes())
+			.map_err(runtime_error_into_rpc_err)
+	}
+
+	fn twap(
+		&self,
+		asset: String,
+		window: u32,
+		at: Option<<Block as BlockT>::Hash>,
+	) -> Result<Option<(u64, Permill)>> {
+		self.client
+			.runtime_api()
+			.twap(&self.at(at), asset.into_bytes(), window)
+			.map_err(runtime_error_into_rpc_err)
+	}
+}
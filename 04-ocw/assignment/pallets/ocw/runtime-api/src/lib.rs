@@ -0,0 +1,22 @@
+//! Runtime API for querying the prices recorded by `pallet_ocw`.
+//!
+//! Assets are identified by their symbol, e.g. `b"DOT"`, and prices are `(integer, fraction)`
+//! pairs as stored in `Prices`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+use sp_arithmetic::per_things::Permill;
+use sp_std::prelude::*;
+
+sp_api::decl_runtime_apis! {
+	pub trait OcwApi {
+		/// The most recent price of `asset`, if any.
+		fn latest_price(asset: Vec<u8>) -> Option<(u64, Permill)>;
+
+		/// The recorded prices of `asset`, oldest first.
+		fn price_history(asset: Vec<u8>) -> Vec<(u64, Permill)>;
+
+		/// Time-weighted average of the latest `window` prices of `asset`.
+		fn twap(asset: Vec<u8>, window: u32) -> Option<(u64, Permill)>;
+	}
+}
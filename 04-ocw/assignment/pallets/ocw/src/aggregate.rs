@@ -0,0 +1,99 @@
+//! Aggregation of the prices quoted by several feeds into one.
+//!
+//! Prices are compared in millionths, the resolution of the `Permill` fractional part.
+
+use sp_arithmetic::per_things::Permill;
+use sp_std::prelude::*;
+
+/// Millionths in one unit.
+const PARTS: u128 = 1_000_000;
+
+fn to_parts((integer, fraction): (u64, Permill)) -> u128 {
+	integer as u128 * PARTS + fraction.deconstruct() as u128
+}
+
+fn from_parts(parts: u128) -> (u64, Permill) {
+	((parts / PARTS) as u64, Permill::from_parts((parts % PARTS) as u32))
+}
+
+/// Median of sorted `parts`; the mean of the two middle values, rounded down, for an even count.
+fn median_of_sorted(parts: &[u128]) -> Option<u128> {
+	let mid = parts.len() / 2;
+	match parts.len() {
+		0 => None,
+		len if len % 2 == 1 => Some(parts[mid]),
+		_ => Some(parts[mid - 1] + (parts[mid] - parts[mid - 1]) / 2),
+	}
+}
+
+/// Whether `parts` deviate at most `max_deviation` from `reference`.
+fn within(parts: u128, reference: u128, max_deviation: Permill) -> bool {
+	let deviation = if parts > reference { parts - reference } else { reference - parts };
+	deviation <= max_deviation * reference
+}
+
+/// Whether `price` deviates more than `max_deviation` from `reference`.
+pub fn deviates(price: (u64, Permill), reference: (u64, Permill), max_deviation: Permill) -> bool {
+	!within(to_parts(price), to_parts(reference), max_deviation)
+}
+
+/// Median of `prices`, `None` if there are none.
+pub fn median(prices: &[(u64, Permill)]) -> Option<(u64, Permill)> {
+	let mut parts: Vec<u128> = prices.iter().copied().map(to_parts).collect();
+	parts.sort_unstable();
+	median_of_sorted(&parts).map(from_parts)
+}
+
+/// Mean of `prices`, rounded down, `None` if there are none.
+pub fn mean(prices: &[(u64, Permill)]) -> Option<(u64, Permill)> {
+	if prices.is_empty() {
+		return None
+	}
+	let sum: u128 = prices.iter().copied().map(to_parts).sum();
+	Some(from_parts(sum / prices.len() as u128))
+}
+
+/// Average of `points`, `(price, timestamp)` pairs oldest first, with every price weighted by
+/// how long it held until the next one. Falls back to the plain mean when no time passed.
+pub fn time_weighted_mean(points: &[((u64, Permill), u64)]) -> Option<(u64, Permill)> {
+	let (first, last) = (points.first()?.1, points.last()?.1);
+	let span = last.saturating_sub(first) as u128;
+	if span == 0 {
+		let prices: Vec<_> = points.iter().map(|(price, _)| *price).collect();
+		return mean(&prices)
+	}
+	let weighted = points.windows(2).fold(0u128, |sum, pair| {
+		let held = pair[1].1.saturating_sub(pair[0].1) as u128;
+		sum.saturating_add(to_parts(pair[0].0).saturating_mul(held))
+	});
+	Some(from_parts(weighted / span))
+}
+
+/// Exponential moving average after `price`, given the `previous` average and the `smoothing`
+/// weight of the new price. Starts at `price` when there is no previous average.
+pub fn ema(
+	previous: Option<(u64, Permill)>,
+	price: (u64, Permill),
+	smoothing: Permill,
+) -> (u64, Permill) {
+	match previous {
+		None => price,
+		Some(previous) => from_parts(
+			(smoothing * to_parts(price))
+				.saturating_add(smoothing.left_from_one() * to_parts(previous)),
+		),
+	}
+}
+
+/// Median of the `prices` deviating at most `max_deviation` from the median of all of them,
+/// together with the number of such prices.
+pub fn aggregate(
+	prices: &[(u64, Permill)],
+	max_deviation: Permill,
+) -> Option<((u64, Permill), u32)> {
+	let mut parts: Vec<u128> = prices.iter().copied().map(to_parts).collect();
+	parts.sort_unstable();
+	let median = median_of_sorted(&parts)?;
+	parts.retain(|p| within(*p, median, max_deviation));
+	median_of_sorted(&parts).map(|agreed| (from_parts(agreed), parts.len() as u32))
+}
@@ -0,0 +1,413 @@
+//! Benchmarking setup for pallet-ocw
+//!
+//! Unsigned calls are measured together with `validate_unsigned`, which verifies the signature of
+//! their payload, as every node pays for that check before the call is dispatched.
+
+use super::*;
+
+use crate::{
+	commit::{Commitment, SubmissionMode},
+	feed::{FeedConfig, Header, HttpMethod, MaxHeaders, MaxJsonPointerLength, MaxUrlLength},
+	schedule::{OffchainTask, TaskSchedule},
+	Pallet as OcwDemo,
+};
+use core::convert::TryFrom;
+use frame_benchmarking::{account, benchmarks, impl_benchmark_test_suite, whitelisted_caller};
+use frame_support::{
+	traits::{Currency, Get, ReservableCurrency, UnfilteredDispatchable},
+	unsigned::ValidateUnsigned,
+	BoundedVec,
+};
+use frame_system::{offchain::SignedPayload, RawOrigin};
+use sp_arithmetic::per_things::Permill;
+use sp_core::sr25519;
+use sp_runtime::{traits::IdentifyAccount, transaction_validity::TransactionSource};
+use sp_std::prelude::*;
+
+const SYMBOL: &[u8] = b"DOT";
+const SALT: [u8; 32] = [7; 32];
+
+/// A new `demo` key in the keystore, its account registered and bonded as an oracle.
+fn oracle<T: Config>() -> T::Public
+where
+	T::Public: From<sr25519::Public>,
+{
+	let public: T::Public = sp_io::crypto::sr25519_generate(KEY_TYPE, None).into();
+	let account = public.clone().into_account();
+	Oracles::<T>::insert(&account, ());
+	OracleCount::<T>::mutate(|count| *count += 1);
+	Bonds::<T>::insert(&account, T::MinReporterBond::get());
+	public
+}
+
+fn sign<T: Config, P: SignedPayload<T>>(payload: &P) -> T::Signature {
+	payload.sign::<T::AuthorityId>().expect("the key of the payload is in the keystore; qed")
+}
+
+/// `SYMBOL`, tracked without feeds.
+fn tracked<T: Config>() -> Result<SymbolOf<T>, &'static str> {
+	let symbol = SymbolOf::<T>::try_from(SYMBOL.to_vec()).map_err(|_| "symbol too long")?;
+	TrackedAssets::<T>::insert(&symbol, AssetInfo { feeds: Default::default() });
+	Ok(symbol)
+}
+
+/// `prefix` padded to the bound `S`.
+fn bounded<S: Get<u32>>(prefix: &[u8]) -> BoundedVec<u8, S> {
+	let mut bytes = prefix.to_vec();
+	bytes.resize(S::get() as usize, b'a');
+	BoundedVec::try_from(bytes).expect("as long as the bound; qed")
+}
+
+/// A feed as large as the bounds allow, which is the slowest to check and store.
+fn largest_feed() -> FeedConfig {
+	let header: Header = (bounded(b"X-"), bounded(b""));
+	FeedConfig {
+		url: bounded(b"https://"),
+		method: HttpMethod::Get,
+		headers: BoundedVec::try_from(vec![header; MaxHeaders::get() as usize])
+			.expect("as many as the bound; qed"),
+		json_pointer: bounded::<MaxJsonPointerLength>(b"/"),
+		decimals: 8,
+	}
+}
+
+/// A balance the currency keeps accounts alive with, `times` over.
+fn balance<T: Config>(times: u32) -> BalanceOf<T> {
+	T::Currency::minimum_balance().max(1u32.into()) * times.into()
+}
+
+/// `MaxHistoryLength` numbers, so that the benchmarked call drops the oldest one.
+fn fill_numbers<T: Config>() {
+	let numbers = (0..T::MaxHistoryLength::get() as u64).collect::<Vec<_>>();
+	Numbers::<T>::put(BoundedVec::try_from(numbers).expect("as many as the bound; qed"));
+}
+
+/// A full price history of `symbol` at `price`, with its averages, so that recording a price
+/// drops the oldest one.
+fn fill_prices<T: Config>(symbol: &SymbolOf<T>, price: (u64, Permill)) {
+	let history =
+		vec![TimestampedPrice { price, timestamp: 0 }; T::MaxHistoryLength::get() as usize];
+	Prices::<T>::insert(symbol, BoundedVec::try_from(history).expect("as many as the bound; qed"));
+	Averages::<T>::insert(symbol, PriceAverages { twap: price, ema: price });
+}
+
+benchmarks! {
+	where_clause { where T::Public: From<sr25519::Public> }
+
+	submit_number_signed {
+		let caller: T::AccountId = whitelisted_caller();
+		fill_numbers::<T>();
+	}: _(RawOrigin::Signed(caller), 42)
+	verify {
+		assert_eq!(Numbers::<T>::get().last(), Some(&42));
+	}
+
+	submit_number_unsigned {
+		fill_numbers::<T>();
+		let call = Call::<T>::submit_number_unsigned(42);
+	}: {
+		OcwDemo::<T>::validate_unsigned(TransactionSource::InBlock, &call)
+			.map_err(<&'static str>::from)?;
+		call.dispatch_bypass_filter(RawOrigin::None.into()).map_err(|e| e.error)?;
+	}
+	verify {
+		assert_eq!(Numbers::<T>::get().last(), Some(&42));
+	}
+
+	submit_number_unsigned_with_signed_payload {
+		fill_numbers::<T>();
+		let payload = Payload { number: 42, public: oracle::<T>() };
+		let signature = sign::<T, _>(&payload);
+		let call = Call::<T>::submit_number_unsigned_with_signed_payload(payload, signature);
+	}: {
+		OcwDemo::<T>::validate_unsigned(TransactionSource::InBlock, &call)
+			.map_err(<&'static str>::from)?;
+		call.dispatch_bypass_filter(RawOrigin::None.into()).map_err(|e| e.error)?;
+	}
+	verify {
+		assert_eq!(Numbers::<T>::get().last(), Some(&42));
+	}
+
+	// Worst case: every other oracle reported already, so the report fills the round.
+	submit_price_unsigned_with_signed_payload {
+		let public = oracle::<T>();
+		let reporter = public.clone().into_account();
+		let symbol = tracked::<T>()?;
+		let price = (10_000, Permill::zero());
+
+		let round: T::BlockNumber = 10u32.into();
+		let reports = (1..T::MaxOracles::get())
+			.map(|i| (account::<T::AccountId>("reporter", i, 0), price))
+			.collect::<Vec<_>>();
+		let reports = BoundedVec::try_from(reports).map_err(|_| "too many reports")?;
+		RoundReports::<T>::insert(round, &symbol, reports);
+		frame_system::Pallet::<T>::set_block_number(round);
+		let payload = PayloadPrice {
+			symbol: SYMBOL.to_vec(),
+			block_number: round,
+			price_tuple: price,
+			sources: T::MinPriceSources::get(),
+			public,
+		};
+		let signature = sign::<T, _>(&payload);
+		let call = Call::<T>::submit_price_unsigned_with_signed_payload(payload, signature);
+	}: {
+		OcwDemo::<T>::validate_unsigned(TransactionSource::InBlock, &call)
+			.map_err(<&'static str>::from)?;
+		call.dispatch_bypass_filter(RawOrigin::None.into()).map_err(|e| e.error)?;
+	}
+	verify {
+		assert_eq!(LastReportRound::<T>::get(&symbol, &reporter), Some(round));
+		assert_eq!(RoundReports::<T>::get(round, &symbol).len() as u32, T::MaxOracles::get());
+	}
+
+	commit_price_unsigned_with_signed_payload {
+		let public = oracle::<T>();
+		let account = public.clone().into_account();
+		let symbol = tracked::<T>()?;
+		PriceSubmissionMode::<T>::put(SubmissionMode::CommitReveal);
+
+		let round: T::BlockNumber = 10u32.into();
+		frame_system::Pallet::<T>::set_block_number(round);
+		let commitment = OcwDemo::<T>::commitment(&account, (10_000, Permill::zero()), &SALT);
+		let payload = PayloadCommit {
+			symbol: SYMBOL.to_vec(),
+			block_number: round,
+			commitment,
+			public,
+		};
+		let signature = sign::<T, _>(&payload);
+		let call = Call::<T>::commit_price_unsigned_with_signed_payload(payload, signature);
+	}: {
+		OcwDemo::<T>::validate_unsigned(TransactionSource::InBlock, &call)
+			.map_err(<&'static str>::from)?;
+		call.dispatch_bypass_filter(RawOrigin::None.into()).map_err(|e| e.error)?;
+	}
+	verify {
+		assert!(Commitments::<T>::contains_key(round, (&symbol, &account)));
+	}
+
+	// Worst case: every other oracle revealed already, so the reveal fills the round.
+	reveal_price_unsigned_with_signed_payload {
+		let public = oracle::<T>();
+		let reporter = public.clone().into_account();
+		let symbol = tracked::<T>()?;
+		PriceSubmissionMode::<T>::put(SubmissionMode::CommitReveal);
+
+		let round: T::BlockNumber = 10u32.into();
+		let price = (10_000, Permill::zero());
+		let hash = OcwDemo::<T>::commitment(&reporter, price, &SALT);
+		Commitments::<T>::insert(round, (&symbol, &reporter), Commitment { hash, revealed: false });
+		let reports = (1..T::MaxOracles::get())
+			.map(|i| (account::<T::AccountId>("reporter", i, 0), price))
+			.collect::<Vec<_>>();
+		let reports = BoundedVec::try_from(reports).map_err(|_| "too many reports")?;
+		RoundReports::<T>::insert(round, &symbol, reports);
+		frame_system::Pallet::<T>::set_block_number(round + T::CommitPeriod::get());
+		let payload = PayloadReveal {
+			symbol: SYMBOL.to_vec(),
+			block_number: round,
+			price_tuple: price,
+			sources: T::MinPriceSources::get(),
+			salt: SALT,
+			public,
+		};
+		let signature = sign::<T, _>(&payload);
+		let call = Call::<T>::reveal_price_unsigned_with_signed_payload(payload, signature);
+	}: {
+		OcwDemo::<T>::validate_unsigned(TransactionSource::InBlock, &call)
+			.map_err(<&'static str>::from)?;
+		call.dispatch_bypass_filter(RawOrigin::None.into()).map_err(|e| e.error)?;
+	}
+	verify {
+		assert_eq!(RoundReports::<T>::get(round, &symbol).len() as u32, T::MaxOracles::get());
+	}
+
+	register_asset {
+		let f in 1 .. T::MaxFeedsPerAsset::get();
+		let feeds = (0..f).map(|i| i.to_le_bytes().to_vec()).collect::<Vec<_>>();
+		for id in feeds.iter() {
+			let id = FeedIdOf::<T>::try_from(id.clone()).map_err(|_| "feed id too long")?;
+			Feeds::<T>::insert(id, largest_feed());
+		}
+	}: _(RawOrigin::Root, SYMBOL.to_vec(), feeds)
+	verify {
+		let symbol = SymbolOf::<T>::try_from(SYMBOL.to_vec()).map_err(|_| "symbol too long")?;
+		assert_eq!(TrackedAssets::<T>::get(&symbol).map(|info| info.feeds.len() as u32), Some(f));
+	}
+
+	set_submission_mode {
+	}: _(RawOrigin::Root, SubmissionMode::CommitReveal)
+	verify {
+		assert_eq!(PriceSubmissionMode::<T>::get(), SubmissionMode::CommitReveal);
+	}
+
+	// Worst case: the asset has every item `deregister_asset` removes, and `o` oracles reported it
+	// in an open round and committed to it in the next one.
+	deregister_asset {
+		let o in 0 .. T::MaxOracles::get();
+		let symbol = tracked::<T>()?;
+		let price = (10_000, Permill::zero());
+		fill_prices::<T>(&symbol, price);
+		PriceGuards::<T>::insert(&symbol, PriceGuard { max_age: 1, max_jump: Permill::one() });
+		StaleAssets::<T>::insert(&symbol, ());
+		Quarantined::<T>::insert(&symbol, TimestampedPrice { price, timestamp: 0 });
+
+		let round: T::BlockNumber = 10u32.into();
+		let next = round + 1u32.into();
+		let oracles = (0..o).map(|i| account::<T::AccountId>("oracle", i, 0)).collect::<Vec<_>>();
+		for oracle in oracles.iter() {
+			LastReportRound::<T>::insert(&symbol, oracle, round);
+			let commitment = Commitment { hash: Default::default(), revealed: false };
+			Commitments::<T>::insert(next, (&symbol, oracle), commitment);
+		}
+		let reports = oracles.into_iter().map(|oracle| (oracle, price)).collect::<Vec<_>>();
+		let reports = BoundedVec::try_from(reports).map_err(|_| "too many reports")?;
+		RoundReports::<T>::insert(round, &symbol, reports);
+	}: _(RawOrigin::Root, SYMBOL.to_vec())
+	verify {
+		assert!(!TrackedAssets::<T>::contains_key(&symbol));
+		assert!(!RoundReports::<T>::contains_key(round, &symbol));
+		assert_eq!(Commitments::<T>::iter_prefix(next).count(), 0);
+		assert_eq!(LastReportRound::<T>::iter_prefix(&symbol).count(), 0);
+	}
+
+	set_price_guard {
+		let symbol = tracked::<T>()?;
+		StaleAssets::<T>::insert(&symbol, ());
+		let guard = PriceGuard { max_age: 60_000, max_jump: Permill::from_percent(20) };
+	}: _(RawOrigin::Root, SYMBOL.to_vec(), Some(guard))
+	verify {
+		assert_eq!(PriceGuards::<T>::get(&symbol), Some(guard));
+	}
+
+	// Worst case: the quarantined price is recorded, dropping the oldest one of a full history.
+	reset_circuit_breaker {
+		let symbol = tracked::<T>()?;
+		fill_prices::<T>(&symbol, (10_000, Permill::zero()));
+		let price = (20_000, Permill::zero());
+		Quarantined::<T>::insert(&symbol, TimestampedPrice { price, timestamp: 0 });
+	}: _(RawOrigin::Root, SYMBOL.to_vec(), true)
+	verify {
+		assert!(!Quarantined::<T>::contains_key(&symbol));
+		assert_eq!(Prices::<T>::get(&symbol).last().map(|p| p.price), Some(price));
+	}
+
+	set_feed {
+		let feed = largest_feed();
+	}: _(RawOrigin::Root, b"polkadot".to_vec(), feed.clone())
+	verify {
+		let id = FeedIdOf::<T>::try_from(b"polkadot".to_vec()).map_err(|_| "feed id too long")?;
+		assert_eq!(Feeds::<T>::get(&id), Some(feed));
+	}
+
+	remove_feed {
+		let id = FeedIdOf::<T>::try_from(b"polkadot".to_vec()).map_err(|_| "feed id too long")?;
+		Feeds::<T>::insert(&id, largest_feed());
+	}: _(RawOrigin::Root, b"polkadot".to_vec())
+	verify {
+		assert!(!Feeds::<T>::contains_key(&id));
+	}
+
+	invalidate_cache {
+	}: _(RawOrigin::Root, b"polkadot".to_vec())
+
+	// Worst case: an oracle asks, and the request takes the last free slot.
+	request_indexing {
+		let caller: T::AccountId = whitelisted_caller();
+		Oracles::<T>::insert(&caller, ());
+		PendingIndexingCount::<T>::put(T::MaxPendingIndexing::get() - 1);
+		let url = bounded::<MaxUrlLength>(b"https://");
+	}: _(RawOrigin::Signed(caller), url)
+	verify {
+		assert!(PendingIndexing::<T>::contains_key(0));
+		assert_eq!(PendingIndexingCount::<T>::get(), T::MaxPendingIndexing::get());
+	}
+
+	submit_indexing_result_unsigned_with_signed_payload {
+		let public = oracle::<T>();
+		PendingIndexing::<T>::insert(0, T::BlockNumber::from(1u32));
+		PendingIndexingCount::<T>::put(1);
+		let result = IndexingResult { length: 0, hash: [0; 32] };
+		let payload = PayloadIndexing { id: 0, result, public };
+		let signature = sign::<T, _>(&payload);
+		let call =
+			Call::<T>::submit_indexing_result_unsigned_with_signed_payload(payload, signature);
+	}: {
+		OcwDemo::<T>::validate_unsigned(TransactionSource::InBlock, &call)
+			.map_err(<&'static str>::from)?;
+		call.dispatch_bypass_filter(RawOrigin::None.into()).map_err(|e| e.error)?;
+	}
+	verify {
+		assert_eq!(IndexingResults::<T>::get(0), Some(result));
+		assert_eq!(PendingIndexingCount::<T>::get(), 0);
+	}
+
+	set_task_schedule {
+		let schedule = TaskSchedule { interval: 10u32.into(), offset: 1u32.into(), enabled: true };
+	}: _(RawOrigin::Root, OffchainTask::Prices, schedule)
+	verify {
+		assert_eq!(TaskSchedules::<T>::get(OffchainTask::Prices), Some(schedule));
+	}
+
+	claim_rewards {
+		let caller: T::AccountId = whitelisted_caller();
+		let amount = balance::<T>(10);
+		T::Currency::make_free_balance_be(&OcwDemo::<T>::pot_account(), balance::<T>(100));
+		PendingRewards::<T>::insert(&caller, amount);
+	}: _(RawOrigin::Signed(caller.clone()))
+	verify {
+		assert_eq!(ClaimedRewards::<T>::get(&caller), amount);
+	}
+
+	bond {
+		let caller: T::AccountId = whitelisted_caller();
+		Oracles::<T>::insert(&caller, ());
+		T::Currency::make_free_balance_be(&caller, balance::<T>(100));
+		let amount = balance::<T>(10);
+	}: _(RawOrigin::Signed(caller.clone()), amount)
+	verify {
+		assert_eq!(Bonds::<T>::get(&caller), amount);
+	}
+
+	// Worst case: an oracle unbonds part of its bond, keeping `MinReporterBond`.
+	unbond {
+		let caller: T::AccountId = whitelisted_caller();
+		Oracles::<T>::insert(&caller, ());
+		let amount = balance::<T>(10);
+		let bond = T::MinReporterBond::get() + amount;
+		T::Currency::make_free_balance_be(&caller, bond + balance::<T>(100));
+		T::Currency::reserve(&caller, bond)?;
+		Bonds::<T>::insert(&caller, bond);
+	}: _(RawOrigin::Signed(caller.clone()), amount)
+	verify {
+		assert_eq!(Bonds::<T>::get(&caller), T::MinReporterBond::get());
+	}
+
+	add_oracle {
+		OracleCount::<T>::put(T::MaxOracles::get() - 1);
+		let who: T::AccountId = account("oracle", 0, 0);
+	}: _(RawOrigin::Root, who.clone())
+	verify {
+		assert!(Oracles::<T>::contains_key(&who));
+		assert_eq!(OracleCount::<T>::get(), T::MaxOracles::get());
+	}
+
+	remove_oracle {
+		let who: T::AccountId = account("oracle", 0, 0);
+		Oracles::<T>::insert(&who, ());
+		OracleCount::<T>::put(1);
+		MissedRounds::<T>::insert(&who, 1);
+		SuspendedUntil::<T>::insert(&who, T::BlockNumber::from(10u32));
+	}: _(RawOrigin::Root, who.clone())
+	verify {
+		assert!(!Oracles::<T>::contains_key(&who));
+		assert_eq!(OracleCount::<T>::get(), 0);
+	}
+}
+
+impl_benchmark_test_suite!(
+	OcwDemo,
+	crate::mock::new_offchain_test_ext().0,
+	crate::mock::Test,
+);
@@ -0,0 +1,127 @@
+//! Offchain cache of the data the offchain worker fetches.
+//!
+//! An entry is fresh for `ttl` milliseconds after it was fetched and served as is. For
+//! `max_stale` milliseconds more it is stale: it is fetched again, but still served if that fails.
+//! After that it is expired and no longer served.
+//!
+//! Entries are invalidated on-chain: the calls changing a feed clear its entry through offchain
+//! indexing, under [`cache_key`].
+
+use core::marker::PhantomData;
+use parity_scale_codec::{Decode, Encode};
+use sp_runtime::{offchain::storage::StorageValueRef, RuntimeDebug};
+use sp_std::prelude::*;
+
+/// Prefix of the persistent offchain storage keys of the cache.
+pub const CACHE_PREFIX: &[u8] = b"offchain-demo::cache::";
+
+/// Persistent offchain storage key of the cache entry `name`.
+pub fn cache_key(name: &[u8]) -> Vec<u8> {
+	[CACHE_PREFIX, name].concat()
+}
+
+/// A cached value and when it was fetched.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct CacheEntry<V, BlockNumber> {
+	pub value: V,
+	/// Unix time in milliseconds.
+	pub fetched_at: u64,
+	/// The block the offchain worker fetched the value in.
+	pub block_number: BlockNumber,
+}
+
+/// How usable a cache entry is.
+#[derive(Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum Freshness {
+	Fresh,
+	Stale,
+	Expired,
+}
+
+impl<V, BlockNumber> CacheEntry<V, BlockNumber> {
+	/// Freshness of the entry at `now`, in Unix milliseconds.
+	pub fn freshness(&self, now: u64, ttl: u64, max_stale: u64) -> Freshness {
+		let age = now.saturating_sub(self.fetched_at);
+		if age < ttl {
+			Freshness::Fresh
+		} else if age < ttl.saturating_add(max_stale) {
+			Freshness::Stale
+		} else {
+			Freshness::Expired
+		}
+	}
+}
+
+/// What the cache holds for an entry.
+#[derive(Clone, PartialEq, Eq, RuntimeDebug)]
+pub enum Cached<V> {
+	/// A value to use without fetching it again.
+	Fresh(V),
+	/// A value to fetch again, and to fall back to if that fails.
+	Stale(V),
+	/// Nothing usable: no entry, an expired one, or one of another type.
+	Missing,
+}
+
+/// One entry of the cache.
+pub struct OffchainCache<BlockNumber> {
+	key: Vec<u8>,
+	ttl: u64,
+	max_stale: u64,
+	_marker: PhantomData<BlockNumber>,
+}
+
+impl<BlockNumber: Encode + Decode> OffchainCache<BlockNumber> {
+	/// The entry `name`, fresh for `ttl` and then stale for `max_stale` milliseconds.
+	pub fn new(name: &[u8], ttl: u64, max_stale: u64) -> Self {
+		OffchainCache { key: cache_key(name), ttl, max_stale, _marker: PhantomData }
+	}
+
+	/// The entry, if there is one holding a `V`.
+	pub fn entry<V: Decode>(&self) -> Option<CacheEntry<V, BlockNumber>> {
+		StorageValueRef::persistent(&self.key).get().ok().flatten()
+	}
+
+	/// The cached value at `now`, in Unix milliseconds.
+	pub fn lookup<V: Decode>(&self, now: u64) -> Cached<V> {
+		match self.entry::<V>() {
+			Some(entry) => match entry.freshness(now, self.ttl, self.max_stale) {
+				Freshness::Fresh => Cached::Fresh(entry.value),
+				Freshness::Stale => Cached::Stale(entry.value),
+				Freshness::Expired => Cached::Missing,
+			},
+			None => Cached::Missing,
+		}
+	}
+
+	/// Cache `value`, fetched at `now` in `block_number`.
+	pub fn set<V: Encode>(&self, value: &V, now: u64, block_number: BlockNumber) {
+		StorageValueRef::persistent(&self.key).set(&CacheEntry {
+			value,
+			fetched_at: now,
+			block_number,
+		});
+	}
+
+	/// The cached value, or the one `fetch` returns if the entry is not fresh. A stale value is
+	/// returned instead of the error if `fetch` fails.
+	pub fn get_or_fetch<V: Encode + Decode, E>(
+		&self,
+		now: u64,
+		block_number: BlockNumber,
+		fetch: impl FnOnce() -> Result<V, E>,
+	) -> Result<V, E> {
+		let stale = match self.lookup(now) {
+			Cached::Fresh(value) => return Ok(value),
+			Cached::Stale(value) => Some(value),
+			Cached::Missing => None,
+		};
+		match fetch() {
+			Ok(value) => {
+				self.set(&value, now, block_number);
+				Ok(value)
+			},
+			Err(e) => stale.ok_or(e),
+		}
+	}
+}
@@ -0,0 +1,86 @@
+//! Commit–reveal price submission.
+//!
+//! In [`SubmissionMode::CommitReveal`] an oracle does not report the price of a round as is. While
+//! the commit window of the round is open it submits a hash of the price and a random salt; once
+//! the reveal window opens it submits the price and the salt, which must match the hash. Prices
+//! already in the pool or on-chain are of no use to an oracle that committed before seeing them.
+//! The offchain worker keeps the salt in persistent offchain storage between the two phases, and
+//! saves it before the commitment is sent.
+
+use parity_scale_codec::{Decode, Encode, MaxEncodedLen};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_arithmetic::per_things::Permill;
+use sp_runtime::{traits::AtLeast32BitUnsigned, RuntimeDebug};
+use sp_std::prelude::*;
+
+/// Persistent offchain storage key of the prices the offchain worker committed to and still has to
+/// reveal.
+pub const REVEALS_KEY: &[u8] = b"offchain-demo::reveals";
+
+/// How oracles submit prices.
+#[derive(Encode, Decode, MaxEncodedLen, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum SubmissionMode {
+	/// Oracles report a price directly; the median of the reports of a round is recorded when
+	/// the round closes.
+	Direct,
+	/// Oracles commit to a price and reveal it later; the median of the valid reveals of a round
+	/// is recorded when the round closes.
+	CommitReveal,
+}
+
+impl Default for SubmissionMode {
+	fn default() -> Self {
+		SubmissionMode::Direct
+	}
+}
+
+/// Where a round is in its commit–reveal cycle.
+#[derive(Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum Phase {
+	/// The round has not started yet.
+	Pending,
+	/// Oracles commit to their price.
+	Commit,
+	/// Oracles reveal the price they committed to.
+	Reveal,
+	/// Neither commitments nor reveals are accepted anymore.
+	Closed,
+}
+
+/// The phase of `round` at `now`, given how many blocks the commit and reveal windows last.
+pub fn phase<BlockNumber: AtLeast32BitUnsigned + Copy>(
+	round: BlockNumber,
+	now: BlockNumber,
+	commit_period: BlockNumber,
+	reveal_period: BlockNumber,
+) -> Phase {
+	let reveal_start = round.saturating_add(commit_period);
+	if now < round {
+		Phase::Pending
+	} else if now < reveal_start {
+		Phase::Commit
+	} else if now < reveal_start.saturating_add(reveal_period) {
+		Phase::Reveal
+	} else {
+		Phase::Closed
+	}
+}
+
+/// The hash an oracle committed to, and whether it revealed the price behind it yet.
+#[derive(Encode, Decode, MaxEncodedLen, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub struct Commitment<Hash> {
+	pub hash: Hash,
+	pub revealed: bool,
+}
+
+/// A price the offchain worker committed to, kept until it is revealed.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct PendingReveal<BlockNumber> {
+	pub symbol: Vec<u8>,
+	pub round: BlockNumber,
+	pub price_tuple: (u64, Permill),
+	pub sources: u32,
+	pub salt: [u8; 32],
+}
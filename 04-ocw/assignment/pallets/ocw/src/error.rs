@@ -0,0 +1,81 @@
+//! Why an offchain worker task failed.
+//!
+//! Offchain failures never reach the chain, so they are kept out of the pallet `Error` and carry
+//! what an operator needs to tell them apart in the node log: the status code of a failed
+//! request, the JSON pointer that matched nothing, the text that is not a number.
+
+use crate::{price::ParseError, retry::HttpFailure};
+use core::fmt;
+use sp_runtime::RuntimeDebug;
+use sp_std::{prelude::*, str};
+
+/// Why an offchain worker task failed.
+#[derive(Clone, PartialEq, Eq, RuntimeDebug)]
+pub enum OffchainError {
+	/// The HTTP request timed out, got a status other than 200, or was not made.
+	Http(HttpFailure),
+	/// The response body is not valid UTF-8.
+	InvalidUtf8,
+	/// The response body is not JSON.
+	InvalidJson,
+	/// The response has no string or number at the JSON pointer `path`.
+	JsonShape { path: Vec<u8> },
+	/// The text `value` is not a price.
+	ParseNumber { value: Vec<u8>, error: ParseError },
+	/// No feed is configured under this id.
+	FeedNotFound(Vec<u8>),
+	/// Fewer feeds than required agreed on a price.
+	NotEnoughSources { agreed: u32, required: u32 },
+	/// The node stores no `IndexingData` for the request with this id.
+	IndexingDataMissing(u64),
+	/// The chain does not accept an unsigned number yet.
+	TooEarly,
+	/// The keystore holds no key to sign with.
+	NoLocalAccount,
+	/// The transaction pool rejected the transaction.
+	SubmitFailed,
+}
+
+impl From<HttpFailure> for OffchainError {
+	fn from(failure: HttpFailure) -> Self {
+		OffchainError::Http(failure)
+	}
+}
+
+/// `bytes` as text for the log.
+pub fn lossy(bytes: &[u8]) -> &str {
+	str::from_utf8(bytes).unwrap_or("<non-utf8>")
+}
+
+impl fmt::Display for OffchainError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			OffchainError::Http(HttpFailure::DeadlineReached) => write!(f, "timeout"),
+			OffchainError::Http(HttpFailure::Status(code)) => write!(f, "http status {}", code),
+			OffchainError::Http(HttpFailure::IoError) => write!(f, "connection failed"),
+			OffchainError::Http(HttpFailure::Invalid) => write!(f, "invalid request"),
+			OffchainError::Http(HttpFailure::CoolingDown) => write!(f, "endpoint cooling down"),
+			OffchainError::InvalidUtf8 => write!(f, "response is not utf-8"),
+			OffchainError::InvalidJson => write!(f, "response is not json"),
+			OffchainError::JsonShape { path } =>
+				write!(f, "no string or number at json pointer `{}`", lossy(path)),
+			OffchainError::ParseNumber { value, error } => {
+				let reason = match error {
+					ParseError::Empty => "no digits",
+					ParseError::InvalidFormat => "not a decimal number",
+					ParseError::Negative => "negative",
+					ParseError::Overflow => "too large",
+				};
+				write!(f, "cannot parse `{}` as a price: {}", lossy(value), reason)
+			},
+			OffchainError::FeedNotFound(id) => write!(f, "feed `{}` not found", lossy(id)),
+			OffchainError::NotEnoughSources { agreed, required } =>
+				write!(f, "{} feeds agreed on a price, {} required", agreed, required),
+			OffchainError::IndexingDataMissing(id) =>
+				write!(f, "no indexing data for request {}", id),
+			OffchainError::TooEarly => write!(f, "unsigned number not accepted yet"),
+			OffchainError::NoLocalAccount => write!(f, "no local account to sign with"),
+			OffchainError::SubmitFailed => write!(f, "transaction pool rejected the transaction"),
+		}
+	}
+}
@@ -0,0 +1,153 @@
+//! Remote data sources queried by the offchain worker.
+//!
+//! A [`FeedConfig`] lives in on-chain storage and is edited by root, so a data source can be
+//! changed without a runtime upgrade. Its parts are bounded, so it has a maximum encoded length.
+
+use core::convert::TryFrom;
+use frame_support::{parameter_types, BoundedVec};
+use parity_scale_codec::{Decode, Encode, MaxEncodedLen};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sp_runtime::RuntimeDebug;
+use sp_std::{prelude::*, str};
+
+parameter_types! {
+	/// Maximum length of the URL of a feed or of an indexing request.
+	pub const MaxUrlLength: u32 = 256;
+	/// Maximum number of request headers of a feed.
+	pub const MaxHeaders: u32 = 8;
+	/// Maximum length of the name and of the value of a request header.
+	pub const MaxHeaderLength: u32 = 128;
+	/// Maximum length of the JSON pointer of a feed.
+	pub const MaxJsonPointerLength: u32 = 128;
+}
+
+/// A request header as a `(name, value)` pair.
+pub type Header = (BoundedVec<u8, MaxHeaderLength>, BoundedVec<u8, MaxHeaderLength>);
+
+/// Whether `url` is a UTF-8 `http(s)` URL, the only kind the offchain worker fetches.
+pub fn is_http_url(url: &[u8]) -> bool {
+	(url.starts_with(b"https://") || url.starts_with(b"http://")) && str::from_utf8(url).is_ok()
+}
+
+/// HTTP method used to query a feed.
+#[derive(Encode, Decode, MaxEncodedLen, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum HttpMethod {
+	Get,
+	/// A `POST` with an empty body.
+	Post,
+}
+
+/// Where and how to fetch a value.
+#[derive(Encode, Decode, MaxEncodedLen, Clone, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct FeedConfig {
+	/// Endpoint to query, e.g. `https://api.coincap.io/v2/assets/polkadot`.
+	#[cfg_attr(feature = "std", serde(with = "bounded_serde::bytes"))]
+	pub url: BoundedVec<u8, MaxUrlLength>,
+	pub method: HttpMethod,
+	/// Request headers as `(name, value)` pairs.
+	#[cfg_attr(feature = "std", serde(with = "bounded_serde::headers"))]
+	pub headers: BoundedVec<Header, MaxHeaders>,
+	/// JSON pointer (RFC 6901) to the value in the response, e.g. `/data/priceUsd`. Empty selects
+	/// the whole document.
+	#[cfg_attr(feature = "std", serde(with = "bounded_serde::bytes"))]
+	pub json_pointer: BoundedVec<u8, MaxJsonPointerLength>,
+	/// Number of implied decimals of the value, e.g. `8` if `712345678` means `7.12345678`.
+	pub decimals: u8,
+}
+
+impl FeedConfig {
+	/// A `GET` of `url` without headers, or `None` if the URL or the pointer is too long.
+	pub fn http_get(url: &[u8], json_pointer: &[u8], decimals: u8) -> Option<Self> {
+		Some(FeedConfig {
+			url: BoundedVec::try_from(url.to_vec()).ok()?,
+			method: HttpMethod::Get,
+			headers: BoundedVec::default(),
+			json_pointer: BoundedVec::try_from(json_pointer.to_vec()).ok()?,
+			decimals,
+		})
+	}
+
+	/// Whether the feed can be queried: an `http(s)` URL, UTF-8 headers and a well-formed pointer.
+	pub fn is_valid(&self) -> bool {
+		let utf8 = |bytes: &[u8]| str::from_utf8(bytes).is_ok();
+		is_http_url(&self.url) &&
+			self.headers
+				.iter()
+				.all(|(name, value)| !name.is_empty() && utf8(name) && utf8(value)) &&
+			(self.json_pointer.is_empty() || self.json_pointer.starts_with(b"/")) &&
+			utf8(&self.json_pointer)
+	}
+
+	/// The part of `document` the feed points at.
+	pub fn select<'a>(&self, document: &'a Value) -> Option<&'a Value> {
+		document.pointer(str::from_utf8(&self.json_pointer).ok()?)
+	}
+
+	/// The text of the string or number the feed points at in `document`.
+	pub fn value_at(&self, document: &Value) -> Option<Vec<u8>> {
+		match self.select(document)? {
+			Value::String(s) => Some(s.as_bytes().to_vec()),
+			Value::Number(n) => serde_json::to_vec(n).ok(),
+			_ => None,
+		}
+	}
+}
+
+/// Serde for the bounded parts of a [`FeedConfig`], written as the byte lists they hold.
+#[cfg(feature = "std")]
+mod bounded_serde {
+	use super::Header;
+	use core::convert::TryFrom;
+	use frame_support::{traits::Get, BoundedVec};
+	use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+	fn bound<T, S: Get<u32>, E: Error>(values: Vec<T>) -> Result<BoundedVec<T, S>, E> {
+		let len = values.len();
+		BoundedVec::try_from(values)
+			.map_err(|_| E::custom(format!("{} values, at most {} allowed", len, S::get())))
+	}
+
+	pub mod bytes {
+		use super::*;
+
+		pub fn serialize<S: Get<u32>, Ser: Serializer>(
+			bytes: &BoundedVec<u8, S>,
+			serializer: Ser,
+		) -> Result<Ser::Ok, Ser::Error> {
+			bytes.as_slice().serialize(serializer)
+		}
+
+		pub fn deserialize<'de, S: Get<u32>, De: Deserializer<'de>>(
+			deserializer: De,
+		) -> Result<BoundedVec<u8, S>, De::Error> {
+			bound(Vec::deserialize(deserializer)?)
+		}
+	}
+
+	pub mod headers {
+		use super::*;
+
+		pub fn serialize<S: Get<u32>, Ser: Serializer>(
+			headers: &BoundedVec<Header, S>,
+			serializer: Ser,
+		) -> Result<Ser::Ok, Ser::Error> {
+			let headers: Vec<(&[u8], &[u8])> =
+				headers.iter().map(|(name, value)| (name.as_slice(), value.as_slice())).collect();
+			headers.serialize(serializer)
+		}
+
+		pub fn deserialize<'de, S: Get<u32>, De: Deserializer<'de>>(
+			deserializer: De,
+		) -> Result<BoundedVec<Header, S>, De::Error> {
+			let headers = Vec::<(Vec<u8>, Vec<u8>)>::deserialize(deserializer)?
+				.into_iter()
+				.map(|(name, value)| Ok((bound(name)?, bound(value)?)))
+				.collect::<Result<Vec<_>, De::Error>>()?;
+			bound(headers)
+		}
+	}
+}
@@ -2,10 +2,51 @@
 
 pub use pallet::*;
 
+pub mod aggregate;
+pub mod cache;
+pub mod commit;
+pub mod error;
+pub mod feed;
+pub mod migrations;
+pub mod price;
+pub mod retry;
+pub mod schedule;
+pub mod traits;
+pub mod weights;
+
+#[cfg(feature = "runtime-benchmarks")]
+mod benchmarking;
+
+#[cfg(test)]
+mod mock;
+
+#[cfg(test)]
+mod tests;
+
 #[frame_support::pallet]
 pub mod pallet {
-    use core::{convert::TryInto, fmt};
-    use frame_support::pallet_prelude::*;
+    use crate::{
+        cache::{cache_key, Cached, OffchainCache},
+        commit::{phase, Commitment, PendingReveal, Phase, SubmissionMode, REVEALS_KEY},
+        error::{lossy, OffchainError},
+        feed::{is_http_url, FeedConfig, HttpMethod, MaxUrlLength},
+        retry::{endpoint_key, EndpointHealth, HttpFailure, RetryPolicy},
+        schedule::{OffchainTask, TaskSchedule},
+        traits::{AveragePrice, LatestPrice},
+        weights::WeightInfo,
+    };
+    use core::{
+        convert::{TryFrom, TryInto},
+        fmt,
+    };
+    use frame_support::{
+        pallet_prelude::*,
+        traits::{
+            BalanceStatus, Currency, ExistenceRequirement, ReservableCurrency, StorageVersion,
+            UnixTime,
+        },
+        BoundedVec, PalletId,
+    };
     use frame_system::{
         offchain::{
             AppCrypto, CreateSignedTransaction, SendSignedTransaction, SendUnsignedTransaction,
@@ -19,26 +60,40 @@ pub mod pallet {
     use sp_runtime::{
         offchain as rt_offchain,
         offchain::{
-            storage::StorageValueRef,
+            storage::{StorageRetrievalError, StorageValueRef},
             storage_lock::{BlockAndTime, StorageLock},
         },
-        traits::BlockNumberProvider,
+        traits::{
+            AccountIdConversion, BlockNumberProvider, Hash, IdentifyAccount, Saturating, Zero,
+        },
         transaction_validity::{
             InvalidTransaction, TransactionSource, TransactionValidity, ValidTransaction,
         },
-        RuntimeDebug,
+        BoundToRuntimeAppPublic, RuntimeDebug,
+    };
+    use sp_std::{
+        collections::btree_set::BTreeSet,
+        prelude::*,
+        str,
     };
-    use sp_std::{collections::vec_deque::VecDeque, prelude::*, str};
 
     use serde::{Deserialize, Deserializer};
+    #[cfg(feature = "std")]
+    use serde::Serialize;
 
     pub const KEY_TYPE: KeyTypeId = KeyTypeId(*b"demo");
-    const NUM_VEC_LEN: usize = 10;
     const UNSIGNED_TXS_PRIORITY: u64 = 100;
+    /// Number of blocks a price payload stays valid after the round it was fetched in.
+    const PRICE_ROUND_LONGEVITY: u32 = 3;
+    /// Number of blocks the offchain worker waits for a submitted indexing result to be included
+    /// before it fetches the URL again.
+    const INDEXING_RESUBMIT_COOLDOWN: u32 = 3;
 
-    const HTTP_REMOTE_REQUEST_DOT_PRICE: &str = "https://api.coincap.io/v2/assets/polkadot";
-    const HTTP_REMOTE_REQUEST: &str = "https://api.github.com/orgs/substrate-developer-hub";
-    const HTTP_HEADER_USER_AGENT: &str = "jimmychu0807";
+    /// Id of the feed `fetch_github_info` reads.
+    pub const GITHUB_FEED_ID: &[u8] = b"github";
+
+    /// Prefix of the offchain storage keys `request_indexing` writes `IndexingData` under.
+    pub const INDEXING_PREFIX: &[u8] = b"ocw-demo::indexing";
 
     const FETCH_TIMEOUT_PERIOD: u64 = 3000;
     const LOCK_TIMEOUT_EXPIRATION: u64 = FETCH_TIMEOUT_PERIOD + 1000;
@@ -73,8 +128,8 @@ pub mod pallet {
 
     #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
     pub struct Payload<Public> {
-        number: u64,
-        public: Public,
+        pub number: u64,
+        pub public: Public,
     }
 
     impl<T: SigningTypes> SignedPayload<T> for Payload<T::Public> {
@@ -84,17 +139,174 @@ pub mod pallet {
     }
 
     #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
-    pub struct PayloadPrice<Public> {
-        price_tuple: (u64, Permill),
-        public: Public,
+    pub struct PayloadPrice<Public, BlockNumber> {
+        pub symbol: Vec<u8>,
+        /// The round the price was fetched in, i.e. the block the offchain worker ran at.
+        pub block_number: BlockNumber,
+        /// Median of the prices quoted by the agreeing feeds.
+        pub price_tuple: (u64, Permill),
+        /// Number of feeds whose price is within `MaxPriceDeviation` of the median.
+        pub sources: u32,
+        pub public: Public,
+    }
+
+    impl<T: SigningTypes> SignedPayload<T> for PayloadPrice<T::Public, T::BlockNumber> {
+        fn public(&self) -> T::Public {
+            self.public.clone()
+        }
+    }
+
+    /// A commitment to the price of `symbol` in the round `block_number`, in commit–reveal mode.
+    #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+    pub struct PayloadCommit<Public, BlockNumber, Hash> {
+        pub symbol: Vec<u8>,
+        pub block_number: BlockNumber,
+        /// `Pallet::commitment` of the price and the salt revealed later.
+        pub commitment: Hash,
+        pub public: Public,
+    }
+
+    impl<T: SigningTypes> SignedPayload<T> for PayloadCommit<T::Public, T::BlockNumber, T::Hash> {
+        fn public(&self) -> T::Public {
+            self.public.clone()
+        }
+    }
+
+    /// The price and salt behind a commitment, in commit–reveal mode.
+    #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+    pub struct PayloadReveal<Public, BlockNumber> {
+        pub symbol: Vec<u8>,
+        pub block_number: BlockNumber,
+        pub price_tuple: (u64, Permill),
+        /// Number of feeds whose price is within `MaxPriceDeviation` of the median.
+        pub sources: u32,
+        pub salt: [u8; 32],
+        pub public: Public,
+    }
+
+    impl<T: SigningTypes> SignedPayload<T> for PayloadReveal<T::Public, T::BlockNumber> {
+        fn public(&self) -> T::Public {
+            self.public.clone()
+        }
+    }
+
+    #[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+    pub struct PayloadIndexing<Public> {
+        /// Id of the processed indexing request.
+        pub id: u64,
+        pub result: IndexingResult,
+        pub public: Public,
     }
 
-    impl<T: SigningTypes> SignedPayload<T> for PayloadPrice<T::Public> {
+    impl<T: SigningTypes> SignedPayload<T> for PayloadIndexing<T::Public> {
         fn public(&self) -> T::Public {
             self.public.clone()
         }
     }
 
+    /// What the offchain worker fetched for an indexing request.
+    #[derive(Encode, Decode, MaxEncodedLen, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+    pub struct IndexingResult {
+        /// Length of the response body in bytes.
+        pub length: u32,
+        /// Blake2-256 hash of the response body.
+        pub hash: [u8; 32],
+    }
+
+    /// Reasons `validate_unsigned` rejects a transaction, returned as `InvalidTransaction::Custom`.
+    #[derive(Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+    #[repr(u8)]
+    pub enum ValidityError {
+        /// The payload is not signed by a registered oracle.
+        NotAnOracle = 1,
+        /// The price round is too old, or the oracle already reported a later one.
+        StaleRound = 2,
+        /// The price round is ahead of the current block.
+        FutureRound = 3,
+        /// An unsigned number arrived before `NextUnsignedAt`.
+        TooEarly = 4,
+        /// The indexing request is unknown or already processed.
+        UnknownRequest = 5,
+        /// The oracle bonded less than `MinReporterBond`.
+        InsufficientBond = 6,
+        /// The oracle is suspended for missing too many rounds.
+        Suspended = 7,
+        /// The commit or reveal window of the round is not open.
+        OutsideWindow = 8,
+        /// The oracle already committed to a price in the round.
+        AlreadyCommitted = 9,
+        /// The reveal matches no commitment of the oracle, or was already made.
+        InvalidReveal = 10,
+    }
+
+    impl From<ValidityError> for InvalidTransaction {
+        fn from(e: ValidityError) -> Self {
+            InvalidTransaction::Custom(e as u8)
+        }
+    }
+
+    impl<T> From<ValidityError> for Error<T> {
+        fn from(e: ValidityError) -> Self {
+            match e {
+                ValidityError::NotAnOracle => Error::NotAnOracle,
+                ValidityError::StaleRound => Error::StaleRound,
+                ValidityError::FutureRound => Error::FutureRound,
+                ValidityError::TooEarly => Error::TooEarly,
+                ValidityError::UnknownRequest => Error::UnknownIndexingRequest,
+                ValidityError::InsufficientBond => Error::InsufficientBond,
+                ValidityError::Suspended => Error::OracleSuspended,
+                ValidityError::OutsideWindow => Error::OutsideWindow,
+                ValidityError::AlreadyCommitted => Error::AlreadyCommitted,
+                ValidityError::InvalidReveal => Error::InvalidReveal,
+            }
+        }
+    }
+
+    /// An asset tracked by the price feed, e.g. `DOT`.
+    #[derive(Encode, Decode, MaxEncodedLen, Clone, PartialEq, Eq, RuntimeDebug)]
+    pub struct AssetInfo<Feeds> {
+        /// Ids of the feeds quoting the price, queried together and aggregated into their median.
+        pub feeds: Feeds,
+    }
+
+    /// A recorded price and when it was recorded.
+    #[derive(Encode, Decode, MaxEncodedLen, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+    #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+    pub struct TimestampedPrice {
+        pub price: (u64, Permill),
+        /// Unix time in milliseconds.
+        pub timestamp: u64,
+    }
+
+    /// Averages over the price history of an asset.
+    #[derive(Encode, Decode, MaxEncodedLen, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+    pub struct PriceAverages {
+        /// Time-weighted average over the recorded prices.
+        pub twap: (u64, Permill),
+        /// Exponential moving average over every price recorded so far.
+        pub ema: (u64, Permill),
+    }
+
+    /// Limits root sets on the recorded prices of an asset.
+    #[derive(Encode, Decode, MaxEncodedLen, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+    #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+    pub struct PriceGuard {
+        /// Milliseconds after which the latest price is stale. `0` never makes it stale.
+        pub max_age: u64,
+        /// How far a new price may move from the latest one before it is quarantined and the
+        /// circuit breaker of the asset trips. `Permill::zero()` never trips it.
+        pub max_jump: Permill,
+    }
+
+    pub type SymbolOf<T> = BoundedVec<u8, <T as Config>::MaxSymbolLength>;
+    pub type FeedIdOf<T> = BoundedVec<u8, <T as Config>::MaxFeedIdLength>;
+    pub type AssetInfoOf<T> =
+        AssetInfo<BoundedVec<FeedIdOf<T>, <T as Config>::MaxFeedsPerAsset>>;
+    /// Prices the offchain worker committed to and has yet to reveal.
+    pub type PendingRevealsOf<T> = Vec<PendingReveal<<T as frame_system::Config>::BlockNumber>>;
+    pub type BalanceOf<T> =
+        <<T as Config>::Currency as Currency<<T as frame_system::Config>::AccountId>>::Balance;
+
     #[derive(Deserialize, Encode, Decode, Default)]
     struct GithubInfo {
         #[serde(deserialize_with = "de_string_to_bytes")]
@@ -104,8 +316,9 @@ pub mod pallet {
         public_repos: u32,
     }
 
-    #[derive(Debug, Deserialize, Encode, Decode, Default)]
-    struct IndexingData(Vec<u8>, u64);
+    /// An indexing request as written to offchain storage: the URL to fetch and the request id.
+    #[derive(Debug, Deserialize, Encode, Decode, Default, Clone, PartialEq, Eq)]
+    pub struct IndexingData(pub Vec<u8>, pub u64);
 
     pub fn de_string_to_bytes<'de, D>(de: D) -> Result<Vec<u8>, D::Error>
     where
@@ -115,6 +328,20 @@ pub mod pallet {
         Ok(s.as_bytes().to_vec())
     }
 
+    /// The latest `S` values of `history`, oldest first.
+    pub(crate) fn latest<V, S: Get<u32>>(mut history: Vec<V>) -> BoundedVec<V, S> {
+        let excess = history.len().saturating_sub(S::get() as usize);
+        history.drain(..excess);
+        BoundedVec::try_from(history).expect("at most `S` values are left; qed")
+    }
+
+    /// Append `value` to `history`, dropping its oldest values once it is full.
+    fn push_latest<V, S: Get<u32>>(history: &mut BoundedVec<V, S>, value: V) {
+        let mut values = sp_std::mem::take(history).into_inner();
+        values.push(value);
+        *history = latest(values);
+    }
+
     impl fmt::Debug for GithubInfo {
         fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
             write!(
@@ -127,100 +354,569 @@ pub mod pallet {
         }
     }
 
-    // Dot价格结构体 (方法1  Json字符串 自动转换成 需要 DotPrice, 和 DotPriceData)
-    // #[derive(Deserialize, Encode, Decode, Default)]
-    // struct DotPrice {
-    //     data: DotPriceData,
-    // }
-    //
-    // #[derive(Deserialize, Encode, Decode, Default)]
-    // struct DotPriceData {
-    //     #[serde(deserialize_with = "de_string_to_bytes")]
-    //     name: Vec<u8>,
-    //     #[serde(deserialize_with = "de_string_to_bytes", alias = "priceUsd")]
-    //     price_usd: Vec<u8>,
-    // }
-
-    // Dot价格结构体 (方法2，手动拼接 DotPriceInfo)
-    #[derive(Deserialize, Encode, Decode, Default)]
-    struct DotPriceInfo {
-        #[serde(deserialize_with = "de_string_to_bytes")]
-        name: Vec<u8>,
-        #[serde(deserialize_with = "de_string_to_bytes")]
-        usd: Vec<u8>,
-    }
-
-    impl fmt::Debug for DotPriceInfo {
-        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-            write!(
-                f,
-                "{{ name: {}, usd: {} }}",
-                str::from_utf8(&self.name).map_err(|_| fmt::Error)?,
-                str::from_utf8(&self.usd).map_err(|_| fmt::Error)?
-            )
-        }
-    }
-
     #[pallet::config]
     pub trait Config: frame_system::Config + CreateSignedTransaction<Call<Self>> {
         type Event: From<Event<Self>> + IsType<<Self as frame_system::Config>::Event>;
         type Call: From<Call<Self>>;
         type AuthorityId: AppCrypto<Self::Public, Self::Signature>;
+
+        /// Maximum length of an asset symbol.
+        #[pallet::constant]
+        type MaxSymbolLength: Get<u32>;
+
+        /// Maximum length of a feed id.
+        #[pallet::constant]
+        type MaxFeedIdLength: Get<u32>;
+
+        /// Maximum number of feeds quoting the price of an asset.
+        #[pallet::constant]
+        type MaxFeedsPerAsset: Get<u32>;
+
+        /// How far a feed may quote from the median of all feeds before it is dropped as an
+        /// outlier.
+        #[pallet::constant]
+        type MaxPriceDeviation: Get<Permill>;
+
+        /// Minimum number of agreeing feeds for a price to be accepted.
+        #[pallet::constant]
+        type MinPriceSources: Get<u32>;
+
+        /// Source of the timestamps recorded with every price.
+        type UnixTime: UnixTime;
+
+        /// Weight of a new price in the exponential moving average.
+        #[pallet::constant]
+        type EmaSmoothing: Get<Permill>;
+
+        /// Currency the oracle rewards are paid and the oracle bonds are reserved in.
+        type Currency: ReservableCurrency<Self::AccountId>;
+
+        /// Id the account funding the oracle rewards is derived from.
+        #[pallet::constant]
+        type PalletId: Get<PalletId>;
+
+        /// Reward an oracle earns for every price report accepted on-chain.
+        #[pallet::constant]
+        type ReporterReward: Get<BalanceOf<Self>>;
+
+        /// Bond an oracle has to reserve before its price reports are accepted.
+        #[pallet::constant]
+        type MinReporterBond: Get<BalanceOf<Self>>;
+
+        /// How far a price report may deviate from the median of its round before the oracle is
+        /// slashed.
+        #[pallet::constant]
+        type ReportTolerance: Get<Permill>;
+
+        /// Part of its bond an oracle loses for every report outside `ReportTolerance`.
+        #[pallet::constant]
+        type DeviationSlash: Get<Permill>;
+
+        /// Maximum number of oracles. Bounds the reports of a round, which `on_initialize` settles
+        /// when the round closes.
+        #[pallet::constant]
+        type MaxOracles: Get<u32>;
+
+        /// Maximum number of indexing requests waiting for the offchain worker.
+        #[pallet::constant]
+        type MaxPendingIndexing: Get<u32>;
+
+        /// Rounds in a row an oracle may miss before it is suspended.
+        #[pallet::constant]
+        type MaxMissedRounds: Get<u32>;
+
+        /// Number of blocks a suspended oracle may not report prices.
+        #[pallet::constant]
+        type SuspensionPeriod: Get<Self::BlockNumber>;
+
+        /// Number of blocks from the start of a round during which oracles commit to a price in
+        /// commit–reveal mode. At least 2, as a commitment is included a block after the round
+        /// at the earliest.
+        #[pallet::constant]
+        type CommitPeriod: Get<Self::BlockNumber>;
+
+        /// Number of blocks after the commit window during which oracles reveal their price.
+        #[pallet::constant]
+        type RevealPeriod: Get<Self::BlockNumber>;
+
+        /// Milliseconds a fetched value is cached before the offchain worker fetches it again.
+        #[pallet::constant]
+        type CacheTtl: Get<u64>;
+
+        /// Milliseconds past `CacheTtl` a cached value is still used when fetching it fails.
+        #[pallet::constant]
+        type CacheMaxStale: Get<u64>;
+
+        /// How the offchain worker retries failed HTTP requests and backs off from failing
+        /// endpoints.
+        #[pallet::constant]
+        type HttpRetryPolicy: Get<RetryPolicy>;
+
+        /// Milliseconds the offchain worker may spend on due tasks in one block. The first due
+        /// task always runs; later ones are skipped once the budget is spent.
+        #[pallet::constant]
+        type OffchainTimeBudget: Get<u64>;
+
+        /// Number of blocks to wait after an unsigned number before the next one is accepted.
+        #[pallet::constant]
+        type UnsignedInterval: Get<Self::BlockNumber>;
+
+        /// Number of numbers, and of prices of every asset, kept in storage. Older ones are
+        /// dropped.
+        #[pallet::constant]
+        type MaxHistoryLength: Get<u32>;
+
+        /// Weights of the benchmarked calls.
+        type WeightInfo: WeightInfo;
     }
 
+    /// Version 1 keeps bounded, timestamped price histories keyed by symbol, see
+    /// `migrations::v1`.
+    const STORAGE_VERSION: StorageVersion = StorageVersion::new(1);
+
     #[pallet::pallet]
     #[pallet::generate_store(pub(super) trait Store)]
+    #[pallet::generate_storage_info]
+    #[pallet::storage_version(STORAGE_VERSION)]
     pub struct Pallet<T>(_);
 
+    /// The latest `MaxHistoryLength` numbers, oldest first.
     #[pallet::storage]
     #[pallet::getter(fn numbers)]
-    pub type Numbers<T> = StorageValue<_, VecDeque<u64>, ValueQuery>;
+    pub type Numbers<T: Config> =
+        StorageValue<_, BoundedVec<u64, T::MaxHistoryLength>, ValueQuery>;
+
+    /// The block from which the next unsigned number is accepted. `submit_number_unsigned` and
+    /// `submit_number_unsigned_with_signed_payload` share this gate, so together they add at most
+    /// one number every `UnsignedInterval` blocks.
+    #[pallet::storage]
+    #[pallet::getter(fn next_unsigned_at)]
+    pub type NextUnsignedAt<T: Config> = StorageValue<_, T::BlockNumber, ValueQuery>;
+
+    /// Schedules of the offchain worker tasks set by root. Other tasks follow their default.
+    #[pallet::storage]
+    pub type TaskSchedules<T: Config> =
+        StorageMap<_, Twox64Concat, OffchainTask, TaskSchedule<T::BlockNumber>>;
+
+    /// Assets whose price the offchain worker fetches, keyed by symbol.
+    #[pallet::storage]
+    #[pallet::getter(fn tracked_assets)]
+    pub type TrackedAssets<T> = StorageMap<_, Blake2_128Concat, SymbolOf<T>, AssetInfoOf<T>>;
+
+    /// Remote data sources, keyed by feed id.
+    #[pallet::storage]
+    #[pallet::getter(fn feeds)]
+    pub type Feeds<T> = StorageMap<_, Blake2_128Concat, FeedIdOf<T>, FeedConfig>;
 
+    /// Accounts whose keys may sign price and number payloads.
+    #[pallet::storage]
+    pub type Oracles<T: Config> = StorageMap<_, Blake2_128Concat, T::AccountId, ()>;
+
+    /// Number of entries in `Oracles`, at most `MaxOracles`.
+    #[pallet::storage]
+    pub type OracleCount<T> = StorageValue<_, u32, ValueQuery>;
+
+    /// The latest round each oracle reported the price of an asset in.
+    #[pallet::storage]
+    pub type LastReportRound<T: Config> = StorageDoubleMap<
+        _,
+        Blake2_128Concat,
+        SymbolOf<T>,
+        Blake2_128Concat,
+        T::AccountId,
+        T::BlockNumber,
+    >;
+
+    /// The latest `MaxHistoryLength` prices of every tracked asset, oldest first.
     #[pallet::storage]
     #[pallet::getter(fn prices)]
-    pub type Prices<T> = StorageValue<_, VecDeque<(u64, Permill)>, ValueQuery>;
+    pub type Prices<T: Config> = StorageMap<
+        _,
+        Blake2_128Concat,
+        SymbolOf<T>,
+        BoundedVec<TimestampedPrice, T::MaxHistoryLength>,
+        ValueQuery,
+    >;
+
+    /// Averages over the prices of every tracked asset, updated with every new price.
+    #[pallet::storage]
+    #[pallet::getter(fn averages)]
+    pub type Averages<T> = StorageMap<_, Blake2_128Concat, SymbolOf<T>, PriceAverages>;
+
+    /// Limits on the recorded prices of every guarded asset.
+    #[pallet::storage]
+    #[pallet::getter(fn price_guards)]
+    pub type PriceGuards<T> = StorageMap<_, Blake2_128Concat, SymbolOf<T>, PriceGuard>;
+
+    /// Assets whose latest price exceeded its maximum age, until a new price is recorded.
+    #[pallet::storage]
+    pub type StaleAssets<T> = StorageMap<_, Blake2_128Concat, SymbolOf<T>, ()>;
+
+    /// The latest price of every asset whose circuit breaker tripped, held back until root
+    /// resets the breaker.
+    #[pallet::storage]
+    #[pallet::getter(fn quarantined)]
+    pub type Quarantined<T> = StorageMap<_, Blake2_128Concat, SymbolOf<T>, TimestampedPrice>;
+
+    /// Id of the next indexing request.
+    #[pallet::storage]
+    pub type NextIndexingId<T> = StorageValue<_, u64, ValueQuery>;
+
+    /// Indexing requests not yet processed, with the block they were made in.
+    #[pallet::storage]
+    #[pallet::getter(fn pending_indexing)]
+    pub type PendingIndexing<T: Config> = StorageMap<_, Twox64Concat, u64, T::BlockNumber>;
+
+    /// Number of entries in `PendingIndexing`, at most `MaxPendingIndexing`.
+    #[pallet::storage]
+    pub type PendingIndexingCount<T> = StorageValue<_, u32, ValueQuery>;
+
+    /// Results of the processed indexing requests.
+    #[pallet::storage]
+    #[pallet::getter(fn indexing_results)]
+    pub type IndexingResults<T> = StorageMap<_, Twox64Concat, u64, IndexingResult>;
+
+    /// Rewards every oracle earned and has not claimed yet.
+    #[pallet::storage]
+    #[pallet::getter(fn pending_rewards)]
+    pub type PendingRewards<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, BalanceOf<T>, ValueQuery>;
+
+    /// Rewards paid out to every oracle so far.
+    #[pallet::storage]
+    #[pallet::getter(fn claimed_rewards)]
+    pub type ClaimedRewards<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, BalanceOf<T>, ValueQuery>;
+
+    /// How oracles submit prices, set by root.
+    #[pallet::storage]
+    #[pallet::getter(fn submission_mode)]
+    pub type PriceSubmissionMode<T> = StorageValue<_, SubmissionMode, ValueQuery>;
+
+    /// The commitments of every open round in commit–reveal mode, keyed by round, then asset and
+    /// oracle.
+    #[pallet::storage]
+    #[pallet::getter(fn commitments)]
+    pub type Commitments<T: Config> = StorageDoubleMap<
+        _,
+        Twox64Concat,
+        T::BlockNumber,
+        Blake2_128Concat,
+        (SymbolOf<T>, T::AccountId),
+        Commitment<T::Hash>,
+    >;
+
+    /// Bond every oracle reserved for its price reports.
+    #[pallet::storage]
+    #[pallet::getter(fn bonds)]
+    pub type Bonds<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, BalanceOf<T>, ValueQuery>;
+
+    /// The prices reported in every open round, keyed by round and asset.
+    #[pallet::storage]
+    pub type RoundReports<T: Config> = StorageDoubleMap<
+        _,
+        Twox64Concat,
+        T::BlockNumber,
+        Blake2_128Concat,
+        SymbolOf<T>,
+        BoundedVec<(T::AccountId, (u64, Permill)), T::MaxOracles>,
+        ValueQuery,
+    >;
+
+    /// Rounds in a row every oracle missed.
+    #[pallet::storage]
+    #[pallet::getter(fn missed_rounds)]
+    pub type MissedRounds<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, u32, ValueQuery>;
+
+    /// The block until which a suspended oracle may not report prices.
+    #[pallet::storage]
+    #[pallet::getter(fn suspended_until)]
+    pub type SuspendedUntil<T: Config> =
+        StorageMap<_, Blake2_128Concat, T::AccountId, T::BlockNumber>;
+
+    #[pallet::genesis_config]
+    pub struct GenesisConfig<T: Config> {
+        /// Initial members of `Oracles`, each bonding `MinReporterBond` of its balance.
+        pub oracles: Vec<T::AccountId>,
+        /// Initial feeds, as `(feed_id, config)` pairs.
+        pub feeds: Vec<(Vec<u8>, FeedConfig)>,
+        /// Initially tracked assets, as `(symbol, feed_ids)` pairs. Every feed is in `feeds`.
+        pub assets: Vec<(Vec<u8>, Vec<Vec<u8>>)>,
+        /// Initial price history of tracked assets, oldest first. Only the latest
+        /// `MaxHistoryLength` prices of an asset are kept.
+        pub prices: Vec<(Vec<u8>, Vec<TimestampedPrice>)>,
+        /// Initial limits on the prices of tracked assets.
+        pub price_guards: Vec<(Vec<u8>, PriceGuard)>,
+        /// How oracles submit prices from genesis on.
+        pub submission_mode: SubmissionMode,
+        /// Schedules of the offchain worker tasks that do not follow their default.
+        pub task_schedules: Vec<(OffchainTask, TaskSchedule<T::BlockNumber>)>,
+    }
+
+    #[cfg(feature = "std")]
+    impl<T: Config> Default for GenesisConfig<T> {
+        fn default() -> Self {
+            Self {
+                oracles: Vec::new(),
+                feeds: Vec::new(),
+                assets: Vec::new(),
+                prices: Vec::new(),
+                price_guards: Vec::new(),
+                submission_mode: SubmissionMode::default(),
+                task_schedules: Vec::new(),
+            }
+        }
+    }
+
+    #[pallet::genesis_build]
+    impl<T: Config> GenesisBuild<T> for GenesisConfig<T> {
+        fn build(&self) {
+            assert!(
+                self.oracles.len() <= T::MaxOracles::get() as usize,
+                "genesis oracles are at most `MaxOracles`"
+            );
+            OracleCount::<T>::put(self.oracles.len() as u32);
+            let bond = T::MinReporterBond::get();
+            for oracle in self.oracles.iter() {
+                Oracles::<T>::insert(oracle, ());
+                if !bond.is_zero() {
+                    T::Currency::reserve(oracle, bond)
+                        .expect("genesis oracles are endowed with their bond");
+                    Bonds::<T>::insert(oracle, bond);
+                }
+            }
+
+            let feed_id = |id: &[u8]| {
+                FeedIdOf::<T>::try_from(id.to_vec()).expect("genesis feed ids are not too long")
+            };
+            for (id, config) in self.feeds.iter() {
+                assert!(config.is_valid(), "genesis feed configs are valid");
+                Feeds::<T>::insert(feed_id(id), config);
+            }
+
+            for (symbol, feeds) in self.assets.iter() {
+                let symbol = SymbolOf::<T>::try_from(symbol.clone())
+                    .expect("genesis symbols are not too long");
+                assert!(!feeds.is_empty(), "genesis assets have feeds");
+                let feeds = feeds
+                    .iter()
+                    .map(|id| {
+                        let id = feed_id(id);
+                        assert!(Feeds::<T>::contains_key(&id), "genesis assets use genesis feeds");
+                        id
+                    })
+                    .collect::<Vec<_>>();
+                let feeds = BoundedVec::try_from(feeds)
+                    .expect("genesis assets have at most `MaxFeedsPerAsset` feeds");
+                TrackedAssets::<T>::insert(&symbol, AssetInfo { feeds });
+            }
+
+            let tracked = |symbol: &[u8]| {
+                Pallet::<T>::tracked_symbol(symbol).expect("genesis prices are of genesis assets")
+            };
+            for (symbol, prices) in self.prices.iter() {
+                let symbol = tracked(symbol);
+                let history = latest::<_, T::MaxHistoryLength>(prices.clone());
+                if let Some(averages) = Pallet::<T>::averages_of(&history) {
+                    Averages::<T>::insert(&symbol, averages);
+                    Prices::<T>::insert(&symbol, history);
+                }
+            }
+
+            for (symbol, guard) in self.price_guards.iter() {
+                PriceGuards::<T>::insert(tracked(symbol), guard);
+            }
+
+            PriceSubmissionMode::<T>::put(self.submission_mode);
+            for (task, schedule) in self.task_schedules.iter() {
+                assert!(!schedule.interval.is_zero(), "genesis task schedules have an interval");
+                TaskSchedules::<T>::insert(task, schedule);
+            }
+        }
+    }
 
     #[pallet::event]
+    #[pallet::metadata(T::AccountId = "AccountId", BalanceOf<T> = "Balance")]
     #[pallet::generate_deposit(pub(super) fn deposit_event)]
     pub enum Event<T: Config> {
         NewNumber(Option<T::AccountId>, u64),
-        NewPrice(Option<T::AccountId>, (u64, Permill)),
+        /// A price was recorded. [who, symbol, price]
+        NewPrice(Option<T::AccountId>, Vec<u8>, (u64, Permill)),
+        /// An asset is now tracked. [symbol]
+        AssetRegistered(Vec<u8>),
+        /// An asset is no longer tracked and its price history was removed. [symbol]
+        AssetDeregistered(Vec<u8>),
+        /// A feed was added or updated. [feed_id]
+        FeedSet(Vec<u8>),
+        /// A feed was removed. [feed_id]
+        FeedRemoved(Vec<u8>),
+        /// An account may now sign payloads. [oracle]
+        OracleAdded(T::AccountId),
+        /// An account may no longer sign payloads. [oracle]
+        OracleRemoved(T::AccountId),
+        /// The schedule of an offchain worker task changed. [task, schedule]
+        TaskScheduleSet(OffchainTask, TaskSchedule<T::BlockNumber>),
+        /// A URL was queued for the offchain worker to fetch, by an oracle or root. [who, id]
+        IndexingRequested(Option<T::AccountId>, u64),
+        /// The offchain worker fetched the URL of an indexing request. [id, result]
+        IndexingProcessed(u64, IndexingResult),
+        /// The offchain cache of a feed was dropped. [feed_id]
+        CacheInvalidated(Vec<u8>),
+        /// An oracle earned a reward for an accepted price. [oracle, amount]
+        RewardEarned(T::AccountId, BalanceOf<T>),
+        /// An oracle was paid its rewards from the pot. [oracle, amount]
+        RewardClaimed(T::AccountId, BalanceOf<T>),
+        /// An oracle reserved more of its balance as bond. [oracle, amount]
+        Bonded(T::AccountId, BalanceOf<T>),
+        /// An oracle got part of its bond back. [oracle, amount]
+        Unbonded(T::AccountId, BalanceOf<T>),
+        /// Part of the bond of an oracle went to the pot for a report too far from the median
+        /// of its round. [oracle, symbol, round, amount]
+        ReporterSlashed(T::AccountId, Vec<u8>, T::BlockNumber, BalanceOf<T>),
+        /// An oracle missed too many rounds in a row and may not report until the block.
+        /// [oracle, until]
+        ReporterSuspended(T::AccountId, T::BlockNumber),
+        /// Oracles now submit prices this way. [mode]
+        SubmissionModeSet(SubmissionMode),
+        /// An oracle committed to a price. [oracle, symbol, round]
+        PriceCommitted(T::AccountId, Vec<u8>, T::BlockNumber),
+        /// An oracle revealed the price it committed to. [oracle, symbol, round, price]
+        PriceRevealed(T::AccountId, Vec<u8>, T::BlockNumber, (u64, Permill)),
+        /// The limits on the prices of an asset changed. [symbol, guard]
+        PriceGuardSet(Vec<u8>, Option<PriceGuard>),
+        /// The latest price of an asset exceeded its maximum age. [symbol, recorded_at]
+        PriceStale(Vec<u8>, u64),
+        /// A price moved too far from the latest one and was quarantined. [symbol, latest, price]
+        CircuitBreakerTripped(Vec<u8>, (u64, Permill), (u64, Permill)),
+        /// A price arrived while the circuit breaker was tripped and replaced the quarantined one.
+        /// [symbol, price]
+        PriceQuarantined(Vec<u8>, (u64, Permill)),
+        /// Root reset a tripped circuit breaker, recording the quarantined price or dropping it.
+        /// [symbol, recorded]
+        CircuitBreakerReset(Vec<u8>, bool),
     }
 
     #[pallet::error]
     pub enum Error<T> {
-        UnknownOffchainMux,
-        NoLocalAcctForSigning,
-        OffchainSignedTxError,
-        OffchainUnsignedTxError,
-        OffchainUnsignedTxSignedPayloadError,
-        HttpFetchingError,
-
-        GetPriceErr,
-        ConvertPriceErr,
+        /// The asset symbol is longer than `MaxSymbolLength`.
+        SymbolTooLong,
+        /// The asset is already tracked.
+        AssetAlreadyTracked,
+        /// The asset is not tracked.
+        AssetNotTracked,
+        /// The feed id is longer than `MaxFeedIdLength`.
+        FeedIdTooLong,
+        /// No feed is configured under this id.
+        FeedNotFound,
+        /// The feed URL, headers or JSON pointer are malformed.
+        InvalidFeedConfig,
+        /// An asset needs at least one feed.
+        NoFeeds,
+        /// An asset has at most `MaxFeedsPerAsset` feeds.
+        TooManyFeeds,
+        /// Fewer than `MinPriceSources` feeds agree on the price.
+        NotEnoughSources,
+        /// The account is already an oracle.
+        AlreadyOracle,
+        /// The account is not an oracle.
+        NotAnOracle,
+        /// There are `MaxOracles` oracles already.
+        TooManyOracles,
+        /// The price round is too old, or the oracle already reported a later one.
+        StaleRound,
+        /// The round holds `MaxOracles` reports of the asset already.
+        RoundFull,
+        /// The price round is ahead of the current block.
+        FutureRound,
+        /// An unsigned number arrived before `NextUnsignedAt`.
+        TooEarly,
+        /// A task schedule needs a non-zero interval.
+        InvalidTaskSchedule,
+        /// The indexing request is unknown or already processed.
+        UnknownIndexingRequest,
+        /// The URL of an indexing request is not a UTF-8 `http(s)` URL.
+        InvalidUrl,
+        /// There are `MaxPendingIndexing` indexing requests waiting already.
+        TooManyIndexingRequests,
+        /// The account has no rewards to claim.
+        NoRewards,
+        /// The oracle bonded less than `MinReporterBond`, or less than it tries to unbond.
+        InsufficientBond,
+        /// The oracle is suspended for missing too many rounds.
+        OracleSuspended,
+        /// Prices are not submitted this way in the current `SubmissionMode`.
+        WrongSubmissionMode,
+        /// The commit or reveal window of the round is not open.
+        OutsideWindow,
+        /// The oracle already committed to a price in the round.
+        AlreadyCommitted,
+        /// The reveal matches no commitment of the oracle, or was already made.
+        InvalidReveal,
+        /// The circuit breaker of the asset is not tripped.
+        BreakerNotTripped,
     }
 
     #[pallet::hooks]
     impl<T: Config> Hooks<BlockNumberFor<T>> for Pallet<T> {
+        fn on_initialize(block_number: T::BlockNumber) -> Weight {
+            let weight = Self::check_staleness();
+            // 报价轮次不再接受报价或揭示后，结算该轮。
+            let closed = block_number.saturating_sub(Self::round_duration());
+            if closed.is_zero() {
+                return weight;
+            }
+            weight.saturating_add(Self::close_round(closed, block_number))
+        }
+
+        fn on_runtime_upgrade() -> Weight {
+            crate::migrations::v1::migrate::<T>()
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn pre_upgrade() -> Result<(), &'static str> {
+            crate::migrations::v1::pre_upgrade::<T>()
+        }
+
+        #[cfg(feature = "try-runtime")]
+        fn post_upgrade() -> Result<(), &'static str> {
+            crate::migrations::v1::post_upgrade::<T>()
+        }
+
         fn offchain_worker(block_number: T::BlockNumber) {
-            const TX_TYPES: u32 = 5;
-            let modu = block_number
-                .try_into()
-                .map_or(TX_TYPES, |bn: usize| (bn as u32) % TX_TYPES);
-
-            log::info!(">>>>>> offchain workers! match mod:{}", modu);
-
-            let result = match modu {
-                1 => Self::offchain_signed_tx(block_number),
-                2 => Self::offchain_unsigned_tx(block_number),
-                3 => Self::offchain_unsigned_tx_signed_payload(block_number),
-                4 => Self::fetch_github_info(),
-                0 => Self::fetch_price_info(),
-                _ => Err(Error::<T>::UnknownOffchainMux),
-            };
+            let deadline = sp_io::offchain::timestamp()
+                .add(rt_offchain::Duration::from_millis(T::OffchainTimeBudget::get()));
+
+            // 依次运行本区块到期的所有任务，超出时间预算后跳过剩余任务。
+            let mut ran_any = false;
+            for task in OffchainTask::ALL.iter().copied() {
+                if !Self::task_schedule(task).is_due(block_number) {
+                    continue;
+                }
+                if ran_any && sp_io::offchain::timestamp() >= deadline {
+                    log::warn!("...... offchain time budget spent, skipping {:?}", task);
+                    continue;
+                }
+                ran_any = true;
 
-            if let Err(e) = result {
-                log::error!("...... offchain_worker error: {:?}", e);
+                log::info!(">>>>>> offchain workers! task: {:?}", task);
+
+                let result = match task {
+                    OffchainTask::SignedNumber => Self::offchain_signed_tx(block_number),
+                    OffchainTask::UnsignedNumber => Self::offchain_unsigned_tx(block_number),
+                    OffchainTask::SignedPayloadNumber => {
+                        Self::offchain_unsigned_tx_signed_payload(block_number)
+                    }
+                    OffchainTask::GithubInfo => Self::fetch_github_info(block_number),
+                    OffchainTask::Prices => Self::fetch_price_info(block_number),
+                    OffchainTask::RevealPrices => Self::reveal_prices(block_number),
+                    OffchainTask::IndexedData => Self::process_indexed_data(block_number),
+                };
+
+                if let Err(e) = result {
+                    log::error!("...... offchain_worker task={:?} error={}", task, e);
+                }
             }
         }
     }
@@ -241,15 +937,23 @@ pub mod pallet {
 
             match call {
                 Call::submit_number_unsigned(_number) => {
+                    if !Self::unsigned_allowed_at(<frame_system::Pallet<T>>::block_number()) {
+                        return InvalidTransaction::from(ValidityError::TooEarly).into();
+                    }
                     valid_tx(b"submit_number_unsigned".to_vec())
                 }
                 Call::submit_number_unsigned_with_signed_payload(ref payload, ref signature) => {
                     if !SignedPayload::<T>::verify::<T::AuthorityId>(payload, signature.clone()) {
                         return InvalidTransaction::BadProof.into();
                     }
+                    if !Self::is_oracle(&payload.public) {
+                        return InvalidTransaction::from(ValidityError::NotAnOracle).into();
+                    }
+                    if !Self::unsigned_allowed_at(<frame_system::Pallet<T>>::block_number()) {
+                        return InvalidTransaction::from(ValidityError::TooEarly).into();
+                    }
                     valid_tx(b"submit_number_unsigned_with_signed_payload".to_vec())
                 }
-                // Call::submit_price_unsigned(_number) => valid_tx(b"submit_price_unsigned".to_vec()),
                 Call::submit_price_unsigned_with_signed_payload(
                     ref payloadprice,
                     ref signature,
@@ -260,7 +964,102 @@ pub mod pallet {
                     ) {
                         return InvalidTransaction::BadProof.into();
                     }
-                    valid_tx(b"submit_price_unsigned_with_signed_payload".to_vec())
+                    if let Err(e) = Self::reporter(&payloadprice.public) {
+                        return InvalidTransaction::from(e).into();
+                    }
+                    let symbol = match Self::tracked_symbol(&payloadprice.symbol) {
+                        Ok(symbol)
+                            if payloadprice.sources >= T::MinPriceSources::get() &&
+                                Self::submission_mode() == SubmissionMode::Direct =>
+                            symbol,
+                        _ => return InvalidTransaction::Call.into(),
+                    };
+                    if let Err(e) = Self::check_round(
+                        &symbol,
+                        &payloadprice.public,
+                        payloadprice.block_number,
+                    ) {
+                        return InvalidTransaction::from(e).into();
+                    }
+                    // 每个资产、每轮、每个 oracle 各自一个 tag，不同 oracle 同一区块内的报价互不冲突。
+                    valid_tx(
+                        (
+                            &b"submit_price_unsigned_with_signed_payload"[..],
+                            &payloadprice.symbol,
+                            payloadprice.block_number,
+                            &payloadprice.public,
+                        )
+                            .encode(),
+                    )
+                }
+                Call::commit_price_unsigned_with_signed_payload(ref payload, ref signature) => {
+                    if !SignedPayload::<T>::verify::<T::AuthorityId>(payload, signature.clone()) {
+                        return InvalidTransaction::BadProof.into();
+                    }
+                    let oracle = match Self::reporter(&payload.public) {
+                        Ok(oracle) => oracle,
+                        Err(e) => return InvalidTransaction::from(e).into(),
+                    };
+                    let symbol = match Self::commit_reveal_symbol(&payload.symbol) {
+                        Ok(symbol) => symbol,
+                        Err(_) => return InvalidTransaction::Call.into(),
+                    };
+                    if let Err(e) = Self::check_commit(&symbol, &oracle, payload.block_number) {
+                        return InvalidTransaction::from(e).into();
+                    }
+                    valid_tx(
+                        (
+                            &b"commit_price_unsigned_with_signed_payload"[..],
+                            &payload.symbol,
+                            payload.block_number,
+                            &payload.public,
+                        )
+                            .encode(),
+                    )
+                }
+                Call::reveal_price_unsigned_with_signed_payload(ref payload, ref signature) => {
+                    if !SignedPayload::<T>::verify::<T::AuthorityId>(payload, signature.clone()) {
+                        return InvalidTransaction::BadProof.into();
+                    }
+                    let oracle = match Self::reporter(&payload.public) {
+                        Ok(oracle) => oracle,
+                        Err(e) => return InvalidTransaction::from(e).into(),
+                    };
+                    let symbol = match Self::commit_reveal_symbol(&payload.symbol) {
+                        Ok(symbol) if payload.sources >= T::MinPriceSources::get() => symbol,
+                        _ => return InvalidTransaction::Call.into(),
+                    };
+                    if let Err(e) = Self::check_reveal(&symbol, &oracle, payload) {
+                        return InvalidTransaction::from(e).into();
+                    }
+                    valid_tx(
+                        (
+                            &b"reveal_price_unsigned_with_signed_payload"[..],
+                            &payload.symbol,
+                            payload.block_number,
+                            &payload.public,
+                        )
+                            .encode(),
+                    )
+                }
+                Call::submit_indexing_result_unsigned_with_signed_payload(
+                    ref payload,
+                    ref signature,
+                ) => {
+                    if !SignedPayload::<T>::verify::<T::AuthorityId>(payload, signature.clone()) {
+                        return InvalidTransaction::BadProof.into();
+                    }
+                    if !Self::is_oracle(&payload.public) {
+                        return InvalidTransaction::from(ValidityError::NotAnOracle).into();
+                    }
+                    if !PendingIndexing::<T>::contains_key(payload.id) {
+                        return InvalidTransaction::from(ValidityError::UnknownRequest).into();
+                    }
+                    // 每个请求只需一个结果，先到的 oracle 为准。
+                    valid_tx(
+                        (&b"submit_indexing_result_unsigned_with_signed_payload"[..], payload.id)
+                            .encode(),
+                    )
                 }
                 _ => InvalidTransaction::Call.into(),
             }
@@ -269,7 +1068,7 @@ pub mod pallet {
 
     #[pallet::call]
     impl<T: Config> Pallet<T> {
-        #[pallet::weight(10000)]
+        #[pallet::weight(T::WeightInfo::submit_number_signed())]
         pub fn submit_number_signed(origin: OriginFor<T>, number: u64) -> DispatchResult {
             let who = ensure_signed(origin)?;
             log::info!("...... submit_number_signed: ({}, {:?})", number, who);
@@ -278,16 +1077,17 @@ pub mod pallet {
             Ok(())
         }
 
-        #[pallet::weight(10000)]
+        #[pallet::weight(T::WeightInfo::submit_number_unsigned())]
         pub fn submit_number_unsigned(origin: OriginFor<T>, number: u64) -> DispatchResult {
             let _ = ensure_none(origin)?;
             log::info!("......  submit_number_unsigned: {}", number);
+            Self::bump_next_unsigned_at()?;
             Self::append_or_replace_number(number);
             Self::deposit_event(Event::NewNumber(None, number));
             Ok(())
         }
 
-        #[pallet::weight(10000)]
+        #[pallet::weight(T::WeightInfo::submit_number_unsigned_with_signed_payload())]
         pub fn submit_number_unsigned_with_signed_payload(
             origin: OriginFor<T>,
             payload: Payload<T::Public>,
@@ -300,118 +1100,889 @@ pub mod pallet {
                 number,
                 public
             );
+            ensure!(Self::is_oracle(&public), Error::<T>::NotAnOracle);
+            Self::bump_next_unsigned_at()?;
             Self::append_or_replace_number(number);
             Self::deposit_event(Event::NewNumber(None, number));
             Ok(())
         }
 
-        // #[pallet::weight(10000)]
-        // pub fn submit_price_unsigned(
-        //     origin: OriginFor<T>,
-        //     price: (u64, Permill),
-        // ) -> DispatchResult {
-        //     let _ = ensure_none(origin)?;
-        //     log::info!("...... submit_price_unsigned: {:?}", price);
-        //     Self::append_or_replace_price(price);
-        //     Self::deposit_event(Event::NewPrice(None, price));
-        //     Ok(())
-        // }
-        
-        #[pallet::weight(10000)]
+        #[pallet::weight(T::WeightInfo::submit_price_unsigned_with_signed_payload())]
         pub fn submit_price_unsigned_with_signed_payload(
             origin: OriginFor<T>,
-            payloadprice: PayloadPrice<T::Public>,
+            payloadprice: PayloadPrice<T::Public, T::BlockNumber>,
             _signature: T::Signature,
         ) -> DispatchResult {
             let _ = ensure_none(origin)?;
             let PayloadPrice {
+                symbol,
+                block_number,
                 price_tuple,
+                sources,
                 public,
             } = payloadprice;
             log::info!(
-                "...... submit_price_unsigned_with_signed_payload: ({:?}, {:?})",
+                "...... submit_price_unsigned_with_signed_payload: ({:?}, {:?}, {:?}, {}, {:?})",
+                symbol,
+                block_number,
                 price_tuple,
+                sources,
+                public
+            );
+            let oracle = Self::reporter(&public).map_err(Error::<T>::from)?;
+            let bounded = Self::tracked_symbol(&symbol)?;
+            ensure!(
+                sources >= T::MinPriceSources::get(),
+                Error::<T>::NotEnoughSources
+            );
+            ensure!(
+                Self::submission_mode() == SubmissionMode::Direct,
+                Error::<T>::WrongSubmissionMode
+            );
+            Self::check_round(&bounded, &public, block_number).map_err(Error::<T>::from)?;
+            RoundReports::<T>::try_append(block_number, &bounded, (oracle.clone(), price_tuple))
+                .map_err(|_| Error::<T>::RoundFull)?;
+            LastReportRound::<T>::insert(&bounded, &oracle, block_number);
+            MissedRounds::<T>::remove(&oracle);
+            Self::reward(oracle);
+            Ok(())
+        }
+
+        /// Commit to a price of `symbol` for a round in commit–reveal mode.
+        #[pallet::weight(T::WeightInfo::commit_price_unsigned_with_signed_payload())]
+        pub fn commit_price_unsigned_with_signed_payload(
+            origin: OriginFor<T>,
+            payload: PayloadCommit<T::Public, T::BlockNumber, T::Hash>,
+            _signature: T::Signature,
+        ) -> DispatchResult {
+            let _ = ensure_none(origin)?;
+            let PayloadCommit { symbol, block_number, commitment, public } = payload;
+            log::info!(
+                "...... commit_price_unsigned_with_signed_payload: ({:?}, {:?}, {:?}, {:?})",
+                symbol,
+                block_number,
+                commitment,
                 public
             );
-            Self::append_or_replace_price(price_tuple);
-            Self::deposit_event(Event::NewPrice(None, price_tuple));
+            let oracle = Self::reporter(&public).map_err(Error::<T>::from)?;
+            let bounded = Self::commit_reveal_symbol(&symbol)?;
+            Self::check_commit(&bounded, &oracle, block_number).map_err(Error::<T>::from)?;
+            Commitments::<T>::insert(
+                block_number,
+                (&bounded, &oracle),
+                Commitment { hash: commitment, revealed: false },
+            );
+            Self::deposit_event(Event::PriceCommitted(oracle, symbol, block_number));
+            Ok(())
+        }
+
+        /// Reveal the price and salt behind a commitment. The price counts towards the median
+        /// recorded when the round closes.
+        #[pallet::weight(T::WeightInfo::reveal_price_unsigned_with_signed_payload())]
+        pub fn reveal_price_unsigned_with_signed_payload(
+            origin: OriginFor<T>,
+            payload: PayloadReveal<T::Public, T::BlockNumber>,
+            _signature: T::Signature,
+        ) -> DispatchResult {
+            let _ = ensure_none(origin)?;
+            log::info!("...... reveal_price_unsigned_with_signed_payload: {:?}", payload);
+            let oracle = Self::reporter(&payload.public).map_err(Error::<T>::from)?;
+            let bounded = Self::commit_reveal_symbol(&payload.symbol)?;
+            ensure!(
+                payload.sources >= T::MinPriceSources::get(),
+                Error::<T>::NotEnoughSources
+            );
+            Self::check_reveal(&bounded, &oracle, &payload).map_err(Error::<T>::from)?;
+            let PayloadReveal { symbol, block_number, price_tuple, .. } = payload;
+            RoundReports::<T>::try_append(block_number, &bounded, (oracle.clone(), price_tuple))
+                .map_err(|_| Error::<T>::RoundFull)?;
+            Commitments::<T>::mutate(block_number, (&bounded, &oracle), |commitment| {
+                if let Some(commitment) = commitment {
+                    commitment.revealed = true;
+                }
+            });
+            MissedRounds::<T>::remove(&oracle);
+            Self::deposit_event(Event::PriceRevealed(
+                oracle.clone(),
+                symbol,
+                block_number,
+                price_tuple,
+            ));
+            Self::reward(oracle);
+            Ok(())
+        }
+
+        /// Set how oracles submit prices. Rounds already committed to are settled as usual.
+        #[pallet::weight(T::WeightInfo::set_submission_mode())]
+        pub fn set_submission_mode(origin: OriginFor<T>, mode: SubmissionMode) -> DispatchResult {
+            ensure_root(origin)?;
+            PriceSubmissionMode::<T>::put(mode);
+            Self::deposit_event(Event::SubmissionModeSet(mode));
+            Ok(())
+        }
+
+        /// Start tracking the price of `symbol`, quoted by the configured `feeds`.
+        #[pallet::weight(T::WeightInfo::register_asset(feeds.len() as u32))]
+        pub fn register_asset(
+            origin: OriginFor<T>,
+            symbol: Vec<u8>,
+            feeds: Vec<Vec<u8>>,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+            let bounded =
+                SymbolOf::<T>::try_from(symbol.clone()).map_err(|_| Error::<T>::SymbolTooLong)?;
+            ensure!(
+                !TrackedAssets::<T>::contains_key(&bounded),
+                Error::<T>::AssetAlreadyTracked
+            );
+            ensure!(!feeds.is_empty(), Error::<T>::NoFeeds);
+            ensure!(
+                feeds.len() <= T::MaxFeedsPerAsset::get() as usize,
+                Error::<T>::TooManyFeeds
+            );
+            let feeds = feeds
+                .iter()
+                .map(|id| {
+                    let id = Self::feed_id(id)?;
+                    ensure!(Feeds::<T>::contains_key(&id), Error::<T>::FeedNotFound);
+                    Ok(id)
+                })
+                .collect::<Result<Vec<_>, Error<T>>>()?;
+            let feeds = BoundedVec::try_from(feeds).map_err(|_| Error::<T>::TooManyFeeds)?;
+            TrackedAssets::<T>::insert(&bounded, AssetInfo { feeds });
+            Self::deposit_event(Event::AssetRegistered(symbol));
+            Ok(())
+        }
+
+        /// Stop tracking `symbol` and drop its price history, along with the reports and
+        /// commitments of its open rounds.
+        #[pallet::weight(T::WeightInfo::deregister_asset(T::MaxOracles::get()))]
+        pub fn deregister_asset(origin: OriginFor<T>, symbol: Vec<u8>) -> DispatchResult {
+            ensure_root(origin)?;
+            let bounded = Self::tracked_symbol(&symbol)?;
+            TrackedAssets::<T>::remove(&bounded);
+            Prices::<T>::remove(&bounded);
+            Averages::<T>::remove(&bounded);
+            PriceGuards::<T>::remove(&bounded);
+            StaleAssets::<T>::remove(&bounded);
+            Quarantined::<T>::remove(&bounded);
+            // 不清理的话，重新登记后旧轮次会拒绝 oracle 的报价，或在轮次结束时记录旧报价。
+            LastReportRound::<T>::remove_prefix(&bounded, None);
+            let rounds: Vec<_> = RoundReports::<T>::iter_keys()
+                .filter(|(_, reported)| *reported == bounded)
+                .collect();
+            for (round, reported) in rounds {
+                RoundReports::<T>::remove(round, reported);
+            }
+            let commitments: Vec<_> = Commitments::<T>::iter_keys()
+                .filter(|(_, (committed, _))| *committed == bounded)
+                .collect();
+            for (round, key) in commitments {
+                Commitments::<T>::remove(round, key);
+            }
+            Self::deposit_event(Event::AssetDeregistered(symbol));
+            Ok(())
+        }
+
+        /// Set or, with `None`, remove the limits on the recorded prices of `symbol`.
+        #[pallet::weight(T::WeightInfo::set_price_guard())]
+        pub fn set_price_guard(
+            origin: OriginFor<T>,
+            symbol: Vec<u8>,
+            guard: Option<PriceGuard>,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+            let bounded = Self::tracked_symbol(&symbol)?;
+            match guard {
+                Some(guard) => PriceGuards::<T>::insert(&bounded, guard),
+                None => PriceGuards::<T>::remove(&bounded),
+            }
+            StaleAssets::<T>::remove(&bounded);
+            Self::deposit_event(Event::PriceGuardSet(symbol, guard));
+            Ok(())
+        }
+
+        /// Reset the tripped circuit breaker of `symbol`. The quarantined price is recorded if
+        /// `record` is set, and dropped otherwise.
+        #[pallet::weight(T::WeightInfo::reset_circuit_breaker())]
+        pub fn reset_circuit_breaker(
+            origin: OriginFor<T>,
+            symbol: Vec<u8>,
+            record: bool,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+            let bounded = Self::tracked_symbol(&symbol)?;
+            let quarantined =
+                Quarantined::<T>::take(&bounded).ok_or(Error::<T>::BreakerNotTripped)?;
+            if record {
+                Self::append_or_replace_price(&bounded, quarantined.price);
+                Self::deposit_event(Event::NewPrice(None, symbol.clone(), quarantined.price));
+            }
+            Self::deposit_event(Event::CircuitBreakerReset(symbol, record));
+            Ok(())
+        }
+
+        /// Add or replace the feed `feed_id`.
+        #[pallet::weight(T::WeightInfo::set_feed())]
+        pub fn set_feed(
+            origin: OriginFor<T>,
+            feed_id: Vec<u8>,
+            config: FeedConfig,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+            let id = Self::feed_id(&feed_id)?;
+            ensure!(config.is_valid(), Error::<T>::InvalidFeedConfig);
+            Feeds::<T>::insert(&id, config);
+            sp_io::offchain_index::clear(&cache_key(&id));
+            Self::deposit_event(Event::FeedSet(feed_id));
+            Ok(())
+        }
+
+        /// Remove the feed `feed_id`. Assets still referring to it ignore it.
+        #[pallet::weight(T::WeightInfo::remove_feed())]
+        pub fn remove_feed(origin: OriginFor<T>, feed_id: Vec<u8>) -> DispatchResult {
+            ensure_root(origin)?;
+            let id = Self::feed_id(&feed_id)?;
+            ensure!(Feeds::<T>::contains_key(&id), Error::<T>::FeedNotFound);
+            Feeds::<T>::remove(&id);
+            sp_io::offchain_index::clear(&cache_key(&id));
+            Self::deposit_event(Event::FeedRemoved(feed_id));
+            Ok(())
+        }
+
+        /// Drop what the offchain worker cached for the feed `feed_id`, so it is fetched again.
+        /// Takes effect on nodes running with offchain indexing enabled.
+        #[pallet::weight(T::WeightInfo::invalidate_cache())]
+        pub fn invalidate_cache(origin: OriginFor<T>, feed_id: Vec<u8>) -> DispatchResult {
+            ensure_root(origin)?;
+            let id = Self::feed_id(&feed_id)?;
+            sp_io::offchain_index::clear(&cache_key(&id));
+            Self::deposit_event(Event::CacheInvalidated(feed_id));
+            Ok(())
+        }
+
+        /// Ask the offchain worker to fetch `url`. The URL only goes to offchain storage, so the
+        /// node has to run with offchain indexing enabled. Only oracles and root may ask, as every
+        /// offchain worker fetches the URL.
+        #[pallet::weight(T::WeightInfo::request_indexing())]
+        pub fn request_indexing(
+            origin: OriginFor<T>,
+            url: BoundedVec<u8, MaxUrlLength>,
+        ) -> DispatchResult {
+            let who = match ensure_root(origin.clone()) {
+                Ok(()) => None,
+                Err(_) => {
+                    let who = ensure_signed(origin)?;
+                    ensure!(Oracles::<T>::contains_key(&who), Error::<T>::NotAnOracle);
+                    Some(who)
+                },
+            };
+            ensure!(is_http_url(&url), Error::<T>::InvalidUrl);
+            PendingIndexingCount::<T>::try_mutate(|count| {
+                ensure!(*count < T::MaxPendingIndexing::get(), Error::<T>::TooManyIndexingRequests);
+                *count += 1;
+                Ok::<_, Error<T>>(())
+            })?;
+            let block_number = <frame_system::Pallet<T>>::block_number();
+            let id = NextIndexingId::<T>::mutate(|next| {
+                let id = *next;
+                *next = next.wrapping_add(1);
+                id
+            });
+            // 只把请求 id 记在链上，URL 经 offchain indexing 写入链下存储，由 offchain worker 读取。
+            sp_io::offchain_index::set(
+                &Self::indexing_key(block_number, id),
+                &IndexingData(url.into_inner(), id).encode(),
+            );
+            PendingIndexing::<T>::insert(id, block_number);
+            Self::deposit_event(Event::IndexingRequested(who, id));
+            Ok(())
+        }
+
+        #[pallet::weight(T::WeightInfo::submit_indexing_result_unsigned_with_signed_payload())]
+        pub fn submit_indexing_result_unsigned_with_signed_payload(
+            origin: OriginFor<T>,
+            payload: PayloadIndexing<T::Public>,
+            _signature: T::Signature,
+        ) -> DispatchResult {
+            let _ = ensure_none(origin)?;
+            let PayloadIndexing { id, result, public } = payload;
+            log::info!(
+                "...... submit_indexing_result_unsigned_with_signed_payload: ({}, {:?}, {:?})",
+                id,
+                result,
+                public
+            );
+            ensure!(Self::is_oracle(&public), Error::<T>::NotAnOracle);
+            let requested_at =
+                PendingIndexing::<T>::take(id).ok_or(Error::<T>::UnknownIndexingRequest)?;
+            PendingIndexingCount::<T>::mutate(|count| *count = count.saturating_sub(1));
+            sp_io::offchain_index::clear(&Self::indexing_key(requested_at, id));
+            sp_io::offchain_index::clear(&Self::indexing_submitted_key(requested_at, id));
+            IndexingResults::<T>::insert(id, result);
+            Self::deposit_event(Event::IndexingProcessed(id, result));
+            Ok(())
+        }
+
+        /// Set when the offchain worker runs `task`.
+        #[pallet::weight(T::WeightInfo::set_task_schedule())]
+        pub fn set_task_schedule(
+            origin: OriginFor<T>,
+            task: OffchainTask,
+            schedule: TaskSchedule<T::BlockNumber>,
+        ) -> DispatchResult {
+            ensure_root(origin)?;
+            ensure!(
+                !schedule.interval.is_zero(),
+                Error::<T>::InvalidTaskSchedule
+            );
+            TaskSchedules::<T>::insert(task, schedule);
+            Self::deposit_event(Event::TaskScheduleSet(task, schedule));
+            Ok(())
+        }
+
+        /// Pay the caller the rewards they earned as an oracle, out of the pot.
+        #[pallet::weight(T::WeightInfo::claim_rewards())]
+        pub fn claim_rewards(origin: OriginFor<T>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let amount = PendingRewards::<T>::get(&who);
+            ensure!(!amount.is_zero(), Error::<T>::NoRewards);
+            T::Currency::transfer(
+                &Self::pot_account(),
+                &who,
+                amount,
+                ExistenceRequirement::KeepAlive,
+            )?;
+            PendingRewards::<T>::remove(&who);
+            ClaimedRewards::<T>::mutate(&who, |claimed| *claimed = claimed.saturating_add(amount));
+            Self::deposit_event(Event::RewardClaimed(who, amount));
+            Ok(())
+        }
+
+        /// Reserve `amount` more of the caller's balance as bond for its price reports.
+        #[pallet::weight(T::WeightInfo::bond())]
+        pub fn bond(origin: OriginFor<T>, amount: BalanceOf<T>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            ensure!(Oracles::<T>::contains_key(&who), Error::<T>::NotAnOracle);
+            T::Currency::reserve(&who, amount)?;
+            Bonds::<T>::mutate(&who, |bond| *bond = bond.saturating_add(amount));
+            Self::deposit_event(Event::Bonded(who, amount));
+            Ok(())
+        }
+
+        /// Unreserve `amount` of the caller's bond. Oracles keep at least `MinReporterBond`
+        /// bonded; removed oracles may unbond all of it.
+        #[pallet::weight(T::WeightInfo::unbond())]
+        pub fn unbond(origin: OriginFor<T>, amount: BalanceOf<T>) -> DispatchResult {
+            let who = ensure_signed(origin)?;
+            let bond = Bonds::<T>::get(&who);
+            ensure!(amount <= bond, Error::<T>::InsufficientBond);
+            let remaining = bond - amount;
+            ensure!(
+                !Oracles::<T>::contains_key(&who) || remaining >= T::MinReporterBond::get(),
+                Error::<T>::InsufficientBond
+            );
+            T::Currency::unreserve(&who, amount);
+            if remaining.is_zero() {
+                Bonds::<T>::remove(&who);
+            } else {
+                Bonds::<T>::insert(&who, remaining);
+            }
+            Self::deposit_event(Event::Unbonded(who, amount));
+            Ok(())
+        }
+
+        /// Allow `who` to sign price and number payloads.
+        #[pallet::weight(T::WeightInfo::add_oracle())]
+        pub fn add_oracle(origin: OriginFor<T>, who: T::AccountId) -> DispatchResult {
+            ensure_root(origin)?;
+            ensure!(!Oracles::<T>::contains_key(&who), Error::<T>::AlreadyOracle);
+            OracleCount::<T>::try_mutate(|count| {
+                ensure!(*count < T::MaxOracles::get(), Error::<T>::TooManyOracles);
+                *count += 1;
+                Ok::<_, Error<T>>(())
+            })?;
+            Oracles::<T>::insert(&who, ());
+            Self::deposit_event(Event::OracleAdded(who));
+            Ok(())
+        }
+
+        /// Stop accepting payloads signed by `who`.
+        #[pallet::weight(T::WeightInfo::remove_oracle())]
+        pub fn remove_oracle(origin: OriginFor<T>, who: T::AccountId) -> DispatchResult {
+            ensure_root(origin)?;
+            ensure!(Oracles::<T>::contains_key(&who), Error::<T>::NotAnOracle);
+            Oracles::<T>::remove(&who);
+            OracleCount::<T>::mutate(|count| *count = count.saturating_sub(1));
+            MissedRounds::<T>::remove(&who);
+            SuspendedUntil::<T>::remove(&who);
+            Self::deposit_event(Event::OracleRemoved(who));
             Ok(())
         }
     }
 
     impl<T: Config> Pallet<T> {
-        fn append_or_replace_price(price: (u64, Permill)) {
-            // 仅保留最新10个Price
-            Prices::<T>::mutate(|prices| {
-                if prices.len() == NUM_VEC_LEN {
-                    let _ = prices.pop_front();
+        /// Whether payloads signed by `public` are accepted.
+        pub fn is_oracle(public: &T::Public) -> bool {
+            Oracles::<T>::contains_key(public.clone().into_account())
+        }
+
+        /// Offchain storage key of the `IndexingData` of request `id`, made in `block_number`.
+        pub fn indexing_key(block_number: T::BlockNumber, id: u64) -> Vec<u8> {
+            (INDEXING_PREFIX, block_number, id).encode()
+        }
+
+        /// Offchain storage key of the block the offchain worker submitted the result of request
+        /// `id`, made in `block_number`, in.
+        pub fn indexing_submitted_key(block_number: T::BlockNumber, id: u64) -> Vec<u8> {
+            let mut key = Self::indexing_key(block_number, id);
+            key.extend_from_slice(b"::submitted");
+            key
+        }
+
+        /// The account the oracle rewards are paid from. Anyone may fund it with a transfer.
+        pub fn pot_account() -> T::AccountId {
+            T::PalletId::get().into_account()
+        }
+
+        fn reward(oracle: T::AccountId) {
+            let amount = T::ReporterReward::get();
+            if amount.is_zero() {
+                return;
+            }
+            PendingRewards::<T>::mutate(&oracle, |pending| {
+                *pending = pending.saturating_add(amount)
+            });
+            Self::deposit_event(Event::RewardEarned(oracle, amount));
+        }
+
+        /// The account of `public` if it may report prices.
+        fn reporter(public: &T::Public) -> Result<T::AccountId, ValidityError> {
+            if !Self::is_oracle(public) {
+                return Err(ValidityError::NotAnOracle);
+            }
+            let oracle = public.clone().into_account();
+            Self::check_reporter(&oracle)?;
+            Ok(oracle)
+        }
+
+        /// Whether `oracle` may report prices: bonded at least `MinReporterBond` and not
+        /// suspended.
+        fn check_reporter(oracle: &T::AccountId) -> Result<(), ValidityError> {
+            if Self::bonds(oracle) < T::MinReporterBond::get() {
+                return Err(ValidityError::InsufficientBond);
+            }
+            let now = <frame_system::Pallet<T>>::block_number();
+            if Self::suspended_until(oracle).map_or(false, |until| now < until) {
+                return Err(ValidityError::Suspended);
+            }
+            Ok(())
+        }
+
+        /// The hash an oracle commits to for `price_tuple` and `salt`.
+        pub fn commitment(
+            oracle: &T::AccountId,
+            price_tuple: (u64, Permill),
+            salt: &[u8; 32],
+        ) -> T::Hash {
+            T::Hashing::hash_of(&(oracle, price_tuple, salt))
+        }
+
+        /// Number of blocks after which a round closes: no reports, commitments or reveals for
+        /// it are accepted anymore.
+        fn round_duration() -> T::BlockNumber {
+            let commit_reveal = T::CommitPeriod::get().saturating_add(T::RevealPeriod::get());
+            commit_reveal.max((PRICE_ROUND_LONGEVITY + 1).into())
+        }
+
+        /// The phase of `round` in commit–reveal mode at the current block.
+        fn round_phase(round: T::BlockNumber) -> Phase {
+            let now = <frame_system::Pallet<T>>::block_number();
+            phase(round, now, T::CommitPeriod::get(), T::RevealPeriod::get())
+        }
+
+        /// `symbol` if it is tracked and prices are committed to and revealed.
+        fn commit_reveal_symbol(symbol: &[u8]) -> Result<SymbolOf<T>, Error<T>> {
+            let bounded = Self::tracked_symbol(symbol)?;
+            ensure!(
+                Self::submission_mode() == SubmissionMode::CommitReveal,
+                Error::<T>::WrongSubmissionMode
+            );
+            Ok(bounded)
+        }
+
+        /// Whether `oracle` may commit to a price of `symbol` in `round`: once, while the commit
+        /// window is open.
+        fn check_commit(
+            symbol: &SymbolOf<T>,
+            oracle: &T::AccountId,
+            round: T::BlockNumber,
+        ) -> Result<(), ValidityError> {
+            if Self::round_phase(round) != Phase::Commit {
+                return Err(ValidityError::OutsideWindow);
+            }
+            if Commitments::<T>::contains_key(round, (symbol, oracle)) {
+                return Err(ValidityError::AlreadyCommitted);
+            }
+            Ok(())
+        }
+
+        /// Whether `payload` reveals an unrevealed commitment of `oracle`, while the reveal
+        /// window is open.
+        fn check_reveal(
+            symbol: &SymbolOf<T>,
+            oracle: &T::AccountId,
+            payload: &PayloadReveal<T::Public, T::BlockNumber>,
+        ) -> Result<(), ValidityError> {
+            if Self::round_phase(payload.block_number) != Phase::Reveal {
+                return Err(ValidityError::OutsideWindow);
+            }
+            let hash = Self::commitment(oracle, payload.price_tuple, &payload.salt);
+            match Commitments::<T>::get(payload.block_number, (symbol, oracle)) {
+                Some(commitment) if !commitment.revealed && commitment.hash == hash => Ok(()),
+                _ => Err(ValidityError::InvalidReveal),
+            }
+        }
+
+        /// Settle the reports of `round` at `now`: record the median of the reports of every
+        /// asset, slash the oracles whose report is outside `ReportTolerance` of the median, and
+        /// count the round as missed for the oracles that could have reported an asset others
+        /// reported but did not.
+        fn close_round(round: T::BlockNumber, now: T::BlockNumber) -> Weight {
+            let db = T::DbWeight::get();
+            let tolerance = T::ReportTolerance::get();
+            let committed = Commitments::<T>::drain_prefix(round).count() as u64;
+            let mut weight = db.reads_writes(committed, committed);
+            let mut missed = BTreeSet::new();
+            let mut expected = None;
+            for (symbol, reports) in RoundReports::<T>::drain_prefix(round) {
+                weight = weight.saturating_add(db.reads_writes(1, 1));
+                let prices: Vec<_> = reports.iter().map(|(_, price)| *price).collect();
+                let median = match crate::aggregate::median(&prices) {
+                    Some(median) => median,
+                    None => continue,
+                };
+                // 每轮每个资产只记录一个价格：所有报价（承诺-揭示模式下为揭示价格）的中位数。
+                Self::record_price(&symbol, median);
+                weight = weight.saturating_add(Self::record_price_weight());
+                for (oracle, price) in reports.iter() {
+                    if crate::aggregate::deviates(*price, median, tolerance) {
+                        Self::slash(oracle, &symbol, round);
+                        // Bonds，以及 oracle 和奖励池两个账户。
+                        weight = weight.saturating_add(db.reads_writes(3, 3));
+                    }
                 }
-                prices.push_back(price);
+                // 只统计本可报价的 oracle：押金足够且未被暂停。
+                let expected = expected.get_or_insert_with(|| {
+                    let oracles = Oracles::<T>::iter_keys()
+                        .filter(|oracle| Self::check_reporter(oracle).is_ok())
+                        .collect::<Vec<_>>();
+                    // Oracles、Bonds 和 SuspendedUntil，最多 MaxOracles 个。
+                    let scanned = OracleCount::<T>::get().min(T::MaxOracles::get()) as u64;
+                    weight = weight.saturating_add(db.reads(1 + scanned * 3));
+                    oracles
+                });
+                missed.extend(
+                    expected
+                        .iter()
+                        .filter(|oracle| !reports.iter().any(|(reporter, _)| reporter == *oracle))
+                        .cloned(),
+                );
+            }
+            for oracle in missed.iter() {
+                Self::miss_round(oracle, now);
+            }
+            // MissedRounds，暂停时还有 SuspendedUntil。
+            weight.saturating_add(db.reads_writes(missed.len() as u64, missed.len() as u64 * 2))
+        }
 
-                log::info!("...... Price vector: {:?}", prices);
+        /// Upper bound of the weight of `record_price`: the circuit breaker, the guard, the
+        /// history and the averages of the asset, and the timestamp.
+        fn record_price_weight() -> Weight {
+            T::DbWeight::get().reads_writes(5, 4)
+        }
+
+        /// Move `DeviationSlash` of the bond of `oracle` to the pot, for its report of `symbol`
+        /// in `round`.
+        fn slash(oracle: &T::AccountId, symbol: &SymbolOf<T>, round: T::BlockNumber) {
+            let amount = T::DeviationSlash::get() * Self::bonds(oracle);
+            if amount.is_zero() {
+                return;
+            }
+            let slashed = match T::Currency::repatriate_reserved(
+                oracle,
+                &Self::pot_account(),
+                amount,
+                BalanceStatus::Free,
+            ) {
+                Ok(missing) => amount.saturating_sub(missing),
+                // 奖励池账户不存在且金额不足存在性押金时无法转入，直接销毁。
+                Err(_) => amount.saturating_sub(T::Currency::slash_reserved(oracle, amount).1),
+            };
+            Bonds::<T>::mutate(oracle, |bond| *bond = bond.saturating_sub(slashed));
+            Self::deposit_event(Event::ReporterSlashed(
+                oracle.clone(),
+                symbol.to_vec(),
+                round,
+                slashed,
+            ));
+        }
+
+        /// Count a missed round for `oracle`, suspending it at `now` once it missed more than
+        /// `MaxMissedRounds` in a row.
+        fn miss_round(oracle: &T::AccountId, now: T::BlockNumber) {
+            let missed = MissedRounds::<T>::mutate(oracle, |missed| {
+                *missed = missed.saturating_add(1);
+                *missed
             });
+            if missed > T::MaxMissedRounds::get() {
+                let until = now.saturating_add(T::SuspensionPeriod::get());
+                MissedRounds::<T>::remove(oracle);
+                SuspendedUntil::<T>::insert(oracle, until);
+                Self::deposit_event(Event::ReporterSuspended(oracle.clone(), until));
+            }
+        }
+
+        /// The schedule `task` currently follows.
+        pub fn task_schedule(task: OffchainTask) -> TaskSchedule<T::BlockNumber> {
+            TaskSchedules::<T>::get(task).unwrap_or_else(|| TaskSchedule::default_for(task))
+        }
+
+        /// The most recent price of `symbol`, if any.
+        pub fn latest_price(symbol: &[u8]) -> Option<(u64, Permill)> {
+            Self::price_history(symbol).pop()
         }
 
-        // 将小数字符，拆分成整数部分和小数部分组成的元组
-        fn convert_price_str(price_str: &str) -> Result<(u64, Permill), Error<T>> {
-            let price_arr: Vec<&str> = price_str.split(".").collect();
-            // log::info!("...... price_arr: {:?}", price_arr);
+        /// The recorded prices of `symbol`, oldest first.
+        pub fn price_history(symbol: &[u8]) -> Vec<(u64, Permill)> {
+            Self::timestamped_prices(symbol)
+                .into_iter()
+                .map(|p| p.price)
+                .collect()
+        }
 
-            let integer_str: &str = &price_arr[0];
-            let decimal_str: &str = &price_arr[1][0..6];
-            // log::info!("...... price_str split: {}.{} ", integer_str, decimal_str);
+        /// Time-weighted average of the latest `window` prices of `symbol`.
+        pub fn twap(symbol: &[u8], window: u32) -> Option<(u64, Permill)> {
+            let history = Self::timestamped_prices(symbol);
+            let start = history.len().saturating_sub(window as usize);
+            crate::aggregate::time_weighted_mean(&history[start..])
+        }
 
-            let integer_parts: u64 = integer_str
-                .parse::<u64>()
-                .map_err(|_| <Error<T>>::ConvertPriceErr)?;
-            let decimal_parts: u32 = decimal_str
-                .parse::<u32>()
-                .map_err(|_| <Error<T>>::ConvertPriceErr)?;
+        fn timestamped_prices(symbol: &[u8]) -> Vec<((u64, Permill), u64)> {
+            SymbolOf::<T>::try_from(symbol.to_vec())
+                .map(|symbol| {
+                    Self::prices(symbol)
+                        .into_iter()
+                        .map(|p| (p.price, p.timestamp))
+                        .collect()
+                })
+                .unwrap_or_default()
+        }
 
-            let price_tuple: (u64, Permill) = (integer_parts, Permill::from_parts(decimal_parts));
-            // log::info!("...... price_tuple: {:?} ", price_tuple);
+        fn symbol_averages(symbol: &[u8]) -> Option<PriceAverages> {
+            SymbolOf::<T>::try_from(symbol.to_vec())
+                .ok()
+                .and_then(Self::averages)
+        }
 
-            Ok(price_tuple)
+        fn unsigned_allowed_at(block_number: T::BlockNumber) -> bool {
+            Self::next_unsigned_at() <= block_number
         }
 
-        fn fetch_price_info() -> Result<(), Error<T>> {
-            // TODO: 这是你们的功课
-            // 利用 offchain worker 取出 DOT 当前对 USD 的价格，并把写到一个 Vec 的存储里，
-            // 你们自己选一种方法提交回链上，并在代码注释为什么用这种方法提交回链上最好。只保留当前最近的 10 个价格，
-            // 其他价格可丢弃 （就是 Vec 的长度长到 10 后，这时再插入一个值时，要先丢弃最早的那个值）。
-            // 取得的价格 parse 完后，放在以下存儲：
-            // pub type Prices<T> = StorageValue<_, VecDeque<(u64, Permill)>, ValueQuery>
-            // 这个 http 请求可得到当前 DOT 价格：
-            // [https://api.coincap.io/v2/assets/polkadot](https://api.coincap.io/v2/assets/polkadot)。
+        /// Reject an unsigned number sent before `NextUnsignedAt`, otherwise push it back by
+        /// `UnsignedInterval`. Both unsigned number calls go through here.
+        fn bump_next_unsigned_at() -> Result<(), Error<T>> {
+            let current = <frame_system::Pallet<T>>::block_number();
+            ensure!(Self::unsigned_allowed_at(current), Error::<T>::TooEarly);
+            NextUnsignedAt::<T>::put(current.saturating_add(T::UnsignedInterval::get()));
+            Ok(())
+        }
 
+        /// Whether an unsigned number sent by the offchain worker at `block_number` can be
+        /// accepted. It is validated against the next block at the earliest.
+        fn unsigned_due(block_number: T::BlockNumber) -> Result<(), OffchainError> {
+            if !Self::unsigned_allowed_at(block_number.saturating_add(1u32.into())) {
+                log::info!(
+                    "...... too early for unsigned tx, next at: {:?}",
+                    Self::next_unsigned_at()
+                );
+                return Err(OffchainError::TooEarly);
+            }
+            Ok(())
+        }
+
+        fn feed_id(id: &[u8]) -> Result<FeedIdOf<T>, Error<T>> {
+            FeedIdOf::<T>::try_from(id.to_vec()).map_err(|_| Error::<T>::FeedIdTooLong)
+        }
+
+        /// Whether `public` may report the price of `symbol` for the round `round`: no later than
+        /// the current block, within `PRICE_ROUND_LONGEVITY` of it, and after its last report.
+        fn check_round(
+            symbol: &SymbolOf<T>,
+            public: &T::Public,
+            round: T::BlockNumber,
+        ) -> Result<(), ValidityError> {
+            let current = <frame_system::Pallet<T>>::block_number();
+            if round > current {
+                return Err(ValidityError::FutureRound);
+            }
+            let last = LastReportRound::<T>::get(symbol, public.clone().into_account());
+            if current - round > PRICE_ROUND_LONGEVITY.into() ||
+                last.map_or(false, |last| round <= last)
+            {
+                return Err(ValidityError::StaleRound);
+            }
+            Ok(())
+        }
+
+        fn tracked_symbol(symbol: &[u8]) -> Result<SymbolOf<T>, Error<T>> {
+            SymbolOf::<T>::try_from(symbol.to_vec())
+                .ok()
+                .filter(|bounded| TrackedAssets::<T>::contains_key(bounded))
+                .ok_or(Error::<T>::AssetNotTracked)
+        }
+
+        /// Record `price` for `symbol`, unless it trips the circuit breaker of the asset or the
+        /// breaker already tripped; the price is quarantined then.
+        fn record_price(symbol: &SymbolOf<T>, price: (u64, Permill)) {
+            let timestamp = T::UnixTime::now().as_millis() as u64;
+            if Quarantined::<T>::contains_key(symbol) {
+                Quarantined::<T>::insert(symbol, TimestampedPrice { price, timestamp });
+                Self::deposit_event(Event::PriceQuarantined(symbol.to_vec(), price));
+                return;
+            }
+            let max_jump = Self::price_guards(symbol).map_or(Permill::zero(), |g| g.max_jump);
+            if let Some(latest) = Self::prices(symbol).last() {
+                if !max_jump.is_zero() &&
+                    crate::aggregate::deviates(price, latest.price, max_jump)
+                {
+                    Quarantined::<T>::insert(symbol, TimestampedPrice { price, timestamp });
+                    Self::deposit_event(Event::CircuitBreakerTripped(
+                        symbol.to_vec(),
+                        latest.price,
+                        price,
+                    ));
+                    return;
+                }
+            }
+            Self::append_or_replace_price(symbol, price);
+            Self::deposit_event(Event::NewPrice(None, symbol.to_vec(), price));
+        }
+
+        /// The averages recording the prices of `history`, oldest first, one after the other
+        /// leaves, or `None` if it is empty.
+        pub(crate) fn averages_of(history: &[TimestampedPrice]) -> Option<PriceAverages> {
+            let points: Vec<_> = history.iter().map(|p| (p.price, p.timestamp)).collect();
+            let twap = crate::aggregate::time_weighted_mean(&points)?;
+            let ema = history.iter().fold(None, |ema, p| {
+                Some(crate::aggregate::ema(ema, p.price, T::EmaSmoothing::get()))
+            });
+            Some(PriceAverages { twap, ema: ema.unwrap_or(twap) })
+        }
+
+        /// Flag every guarded asset whose latest price just exceeded its maximum age.
+        fn check_staleness() -> Weight {
+            let now = T::UnixTime::now().as_millis() as u64;
+            let (mut reads, mut writes) = (0, 0);
+            for (symbol, guard) in PriceGuards::<T>::iter() {
+                reads += 2;
+                if guard.max_age == 0 || StaleAssets::<T>::contains_key(&symbol) {
+                    continue;
+                }
+                reads += 1;
+                let recorded_at = match Self::prices(&symbol).last() {
+                    Some(latest) => latest.timestamp,
+                    None => continue,
+                };
+                if now.saturating_sub(recorded_at) > guard.max_age {
+                    writes += 1;
+                    StaleAssets::<T>::insert(&symbol, ());
+                    Self::deposit_event(Event::PriceStale(symbol.to_vec(), recorded_at));
+                }
+            }
+            T::DbWeight::get().reads_writes(reads, writes)
+        }
+
+        fn append_or_replace_price(symbol: &SymbolOf<T>, price: (u64, Permill)) {
+            let timestamp = T::UnixTime::now().as_millis() as u64;
+            StaleAssets::<T>::remove(symbol);
+            // 仅保留最新 MaxHistoryLength 个 Price
+            let points = Prices::<T>::mutate(symbol, |prices| {
+                push_latest(prices, TimestampedPrice { price, timestamp });
+
+                log::info!("...... Price vector: {:?}", prices);
+                prices
+                    .iter()
+                    .map(|p| (p.price, p.timestamp))
+                    .collect::<Vec<_>>()
+            });
+
+            // 每次记录价格后重新计算 TWAP 和 EMA
+            Averages::<T>::mutate(symbol, |averages| {
+                let ema = crate::aggregate::ema(
+                    averages.map(|a| a.ema),
+                    price,
+                    T::EmaSmoothing::get(),
+                );
+                let twap = crate::aggregate::time_weighted_mean(&points).unwrap_or(price);
+                *averages = Some(PriceAverages { twap, ema });
+                log::info!("...... Price averages: {:?}", averages);
+            });
+        }
+
+        fn fetch_price_info(block_number: T::BlockNumber) -> Result<(), OffchainError> {
             log::info!("...... fetch_price_info");
 
-            let dot_price =
-                Self::fetch_n_parse_to_dot_price().map_err(|_| <Error<T>>::GetPriceErr)?;
+            // 逐个获取已登记资产的价格，单个资产失败不影响其他资产。
+            let mut result = Ok(());
+            for (symbol, info) in TrackedAssets::<T>::iter() {
+                if let Err(e) = Self::fetch_and_submit_price(&symbol, &info, block_number) {
+                    log::error!(
+                        "...... fetch price symbol={} error={}",
+                        lossy(&symbol),
+                        e
+                    );
+                    result = Err(e);
+                }
+            }
+            result
+        }
 
-            // 获取 dot usd 对应的字符串
-            let usd_str: &str = str::from_utf8(&dot_price.usd).unwrap();
+        fn fetch_and_submit_price(
+            symbol: &[u8],
+            info: &AssetInfoOf<T>,
+            block_number: T::BlockNumber,
+        ) -> Result<(), OffchainError> {
+            // 同时查询所有 feed，剔除失败及偏离中位数过多的报价后取中位数。
+            let prices = Self::fetch_n_parse_prices(info, block_number);
+            let required = T::MinPriceSources::get();
+            let (price_tuple, sources) =
+                crate::aggregate::aggregate(&prices, T::MaxPriceDeviation::get())
+                    .ok_or(OffchainError::NotEnoughSources { agreed: 0, required })?;
+            log::info!(
+                "...... price_tuple: {:?}, agreed by {} of {} feeds",
+                price_tuple,
+                sources,
+                info.feeds.len()
+            );
+            ensure!(
+                sources >= required,
+                OffchainError::NotEnoughSources { agreed: sources, required }
+            );
 
-            //折分成整部分和小数部分的元组
-            let price_tuple: (u64, Permill) =
-                Self::convert_price_str(usd_str).map_err(|_| <Error<T>>::ConvertPriceErr)?;
-            log::info!("...... price_tuple: {:?}", price_tuple);
-
-            // 使用不签名方式，提交到链上。
-            // let call = Call::submit_price_unsigned(price_tuple);
-            // SubmitTransaction::<T, Call<T>>::submit_unsigned_transaction(call.into()).map_err(
-            //     |_| {
-            //         log::error!("...... Failed in offchain_unsigned_tx");
-            //         <Error<T>>::OffchainUnsignedTxError
-            //     },
-            // )
+            if Self::submission_mode() == SubmissionMode::CommitReveal {
+                return Self::commit_price(symbol, block_number, price_tuple, sources);
+            }
 
             // 使用不签名但具签名信息的交易，提交到链上。
             // 因为很多时候签名交易意味签名者需要为该交易付手续费。但有些情况只想知道该交易来源是谁，但不需要该用户付手续费。
             let signer = Signer::<T, T::AuthorityId>::any_account();
             let result = signer.send_unsigned_transaction(
                 |acct| PayloadPrice {
-                    price_tuple: price_tuple,
+                    symbol: symbol.to_vec(),
+                    block_number,
+                    price_tuple,
+                    sources,
                     public: acct.public.clone(),
                 },
                 Call::submit_price_unsigned_with_signed_payload,
@@ -419,88 +1990,295 @@ pub mod pallet {
             if let Some((_, res)) = result {
                 return res.map_err(|_| {
                     log::error!("...... Failed in offchain_unsigned_tx_signed_payload");
-                    <Error<T>>::OffchainUnsignedTxSignedPayloadError
+                    OffchainError::SubmitFailed
                 });
             }
             log::error!("...... No local account available");
-            Err(<Error<T>>::NoLocalAcctForSigning)
-
-            
-            // 记录到 ocw 链下的独立存储
-            // let s_price = StorageValueRef::persistent(b"offchain-demo::dot-price");
-            // let mut lock = StorageLock::<BlockAndTime<Self>>::with_block_and_time_deadline(
-            //     b"offchain-demo::lock",
-            //     LOCK_BLOCK_EXPIRATION,
-            //     rt_offchain::Duration::from_millis(LOCK_TIMEOUT_EXPIRATION),
-            // );
-            // if let Ok(_guard) = lock.try_lock() {
-            //     match Self::fetch_n_parse_to_dot_price() {
-            //         Ok(dot_price) => {
-            //             log::info!("......dot_price: {:?}", dot_price);
-            //             //获取 dot usd 对应的字符串
-            //             let usd_str: &str = str::from_utf8(&dot_price.usd).unwrap();
-            //             //折分成整部分和小数部分的元组
-            //             let price_tuple: (u64, Permill) = Self::convert_price_str(usd_str).map_err(|_| <Error<T>>::ConvertPriceErr)?;
-            //             log::info!("......{:?}", price_tuple);
-            //             // 写入链下的独立存储
-            //             s_price.set(&dot_price);
-            //             // 读取链下的独立存储
-            //             if let Ok(Some(s_dotprice)) = s_price.get::<DotPriceInfo>() {
-            //                 log::info!("......cached s_dotprice: {:?}", s_dotprice);
-            //             }
-            //         }
-            //         Err(err) => { return Err(err);}
-            //     }
-            // }
-            // Ok()
-        }
-
-        fn fetch_n_parse_to_dot_price() -> Result<DotPriceInfo, Error<T>> {
-            // 获取HTTP数据
-            let resp_bytes =
-                Self::fetch_from_remote(HTTP_REMOTE_REQUEST_DOT_PRICE).map_err(|e| {
-                    log::error!("...... fetch_from_remote error: {:?}", e);
-                    <Error<T>>::HttpFetchingError
-                })?;
-
-            // HTTP 数据，转换成 Json 字符串
-            let resp_str =
-                str::from_utf8(&resp_bytes).map_err(|_| <Error<T>>::HttpFetchingError)?;
-            log::info!("{}", resp_str);
-
-            // 方法1： Json字符串 自动转换成 dot_price
-            // let s_data: DotPrice =
-            //     serde_json::from_str(&resp_str).map_err(|_| <Error<T>>::HttpFetchingError)?;
-            // log::info!("...... s_data.data.name: {}",str::from_utf8(&s_data.data.name).unwrap());
-            // log::info!("...... s_data.data.price_usd: {:?}",str::from_utf8(&s_data.data.price_usd).unwrap());
-
-            // 方法2： 转换为serde_json 再转换成 dot_price
-            let v: serde_json::Value =
-                serde_json::from_str(&resp_str).map_err(|_| <Error<T>>::HttpFetchingError)?;
-            let dot_price: DotPriceInfo = DotPriceInfo {
-                name: v["data"]["name"].as_str().unwrap().as_bytes().to_vec(),
-                usd: v["data"]["priceUsd"].as_str().unwrap().as_bytes().to_vec(),
+            Err(OffchainError::NoLocalAccount)
+        }
+
+        /// Commit to `price_tuple` for the round `block_number`, keeping the salt in persistent
+        /// offchain storage until `reveal_prices` reveals it.
+        fn commit_price(
+            symbol: &[u8],
+            block_number: T::BlockNumber,
+            price_tuple: (u64, Permill),
+            sources: u32,
+        ) -> Result<(), OffchainError> {
+            let reveal = PendingReveal {
+                symbol: symbol.to_vec(),
+                round: block_number,
+                price_tuple,
+                sources,
+                salt: sp_io::offchain::random_seed(),
+            };
+            // 先保存盐再发送承诺：承诺一旦上链，丢了盐就再也无法揭示。
+            Self::update_pending_reveals(|pending| pending.push(reveal.clone()))?;
+
+            let signer = Signer::<T, T::AuthorityId>::any_account();
+            let result = signer.send_unsigned_transaction(
+                |acct| PayloadCommit {
+                    symbol: symbol.to_vec(),
+                    block_number,
+                    commitment: Self::commitment(
+                        &acct.public.clone().into_account(),
+                        price_tuple,
+                        &reveal.salt,
+                    ),
+                    public: acct.public.clone(),
+                },
+                Call::commit_price_unsigned_with_signed_payload,
+            );
+            let error = match result {
+                Some((_, Ok(()))) => return Ok(()),
+                Some((_, Err(()))) => {
+                    log::error!("...... Failed in commit_price");
+                    OffchainError::SubmitFailed
+                },
+                None => {
+                    log::error!("...... No local account available");
+                    OffchainError::NoLocalAccount
+                },
+            };
+            // 承诺没有发出，没有什么可揭示的。
+            let _ = Self::update_pending_reveals(|pending| pending.retain(|p| p != &reveal));
+            Err(error)
+        }
+
+        /// Apply `f` to the pending reveals in persistent offchain storage.
+        fn update_pending_reveals(
+            f: impl FnOnce(&mut PendingRevealsOf<T>),
+        ) -> Result<(), OffchainError> {
+            StorageValueRef::persistent(REVEALS_KEY)
+                .mutate(|pending: Result<Option<PendingRevealsOf<T>>, StorageRetrievalError>| {
+                    let mut pending = pending.ok().flatten().unwrap_or_default();
+                    f(&mut pending);
+                    Ok::<_, ()>(pending)
+                })
+                .map(|_| ())
+                .map_err(|_| OffchainError::SubmitFailed)
+        }
+
+        /// Reveal the committed prices whose reveal window is open when the transaction is
+        /// validated, and drop those whose window closed.
+        fn reveal_prices(block_number: T::BlockNumber) -> Result<(), OffchainError> {
+            // 交易最早在下一个区块验证，按下一个区块判断揭示窗口。
+            let next = block_number + 1u32.into();
+            let window = |round: T::BlockNumber| {
+                phase(round, next, T::CommitPeriod::get(), T::RevealPeriod::get())
+            };
+            let mut due = Vec::new();
+            let taken = StorageValueRef::persistent(REVEALS_KEY).mutate(
+                |pending: Result<Option<PendingRevealsOf<T>>, StorageRetrievalError>| {
+                    let (reveal, keep): (Vec<_>, Vec<_>) = pending
+                        .ok()
+                        .flatten()
+                        .unwrap_or_default()
+                        .into_iter()
+                        .filter(|pending| window(pending.round) != Phase::Closed)
+                        .partition(|pending| window(pending.round) == Phase::Reveal);
+                    due = reveal;
+                    Ok::<_, ()>(keep)
+                },
+            );
+            // 另一个 offchain worker 同时修改了列表，下一个区块再揭示。
+            if taken.is_err() || due.is_empty() {
+                return Ok(());
+            }
+
+            let signer = Signer::<T, T::AuthorityId>::any_account();
+            let mut result = Ok(());
+            for reveal in due {
+                let sent = signer.send_unsigned_transaction(
+                    |acct| PayloadReveal {
+                        symbol: reveal.symbol.clone(),
+                        block_number: reveal.round,
+                        price_tuple: reveal.price_tuple,
+                        sources: reveal.sources,
+                        salt: reveal.salt,
+                        public: acct.public.clone(),
+                    },
+                    Call::reveal_price_unsigned_with_signed_payload,
+                );
+                let error = match sent {
+                    Some((_, Ok(()))) => continue,
+                    Some((_, Err(()))) => OffchainError::SubmitFailed,
+                    None => OffchainError::NoLocalAccount,
+                };
+                log::error!(
+                    "...... reveal price symbol={} round={:?} error={}",
+                    lossy(&reveal.symbol),
+                    reveal.round,
+                    error
+                );
+                result = Err(error);
+            }
+            result
+        }
+
+        fn fetch_n_parse_prices(
+            info: &AssetInfoOf<T>,
+            block_number: T::BlockNumber,
+        ) -> Vec<(u64, Permill)> {
+            let now = sp_io::offchain::timestamp();
+            let deadline = now.add(rt_offchain::Duration::from_millis(FETCH_TIMEOUT_PERIOD));
+
+            // 缓存仍新鲜的 feed 直接用缓存，其余的一起请求。
+            let mut prices = Vec::new();
+            let mut feeds = Vec::new();
+            for feed_id in info.feeds.iter() {
+                let feed = match Feeds::<T>::get(feed_id) {
+                    Some(feed) => feed,
+                    None => {
+                        log::warn!(
+                            "...... feed={} error={}",
+                            lossy(feed_id),
+                            OffchainError::FeedNotFound(feed_id.to_vec())
+                        );
+                        continue;
+                    }
+                };
+                let cache = Self::feed_cache(feed_id);
+                let stale = match cache.lookup(now.unix_millis()) {
+                    Cached::Fresh(price) => {
+                        prices.push(price);
+                        continue;
+                    }
+                    Cached::Stale(price) => Some(price),
+                    Cached::Missing => None,
+                };
+                feeds.push((feed_id, feed, cache, stale));
+            }
+
+            let requests: Vec<_> = feeds.iter().map(|(_, feed, _, _)| feed).collect();
+            let bodies = Self::fetch_bodies(&requests, deadline);
+            for (body, (feed_id, feed, cache, stale)) in bodies.into_iter().zip(feeds) {
+                match body.and_then(|body| Self::parse_feed_price(&feed, &body)) {
+                    Ok(price) => {
+                        cache.set(&price, now.unix_millis(), block_number);
+                        prices.push(price);
+                    }
+                    Err(e) => {
+                        log::warn!(
+                            "...... feed={} error={}",
+                            lossy(feed_id),
+                            e
+                        );
+                        // 获取失败时退回到尚未过期的旧价格。
+                        prices.extend(stale);
+                    }
+                }
+            }
+            prices
+        }
+
+        /// The offchain cache of what the feed `feed_id` returned.
+        fn feed_cache(feed_id: &[u8]) -> OffchainCache<T::BlockNumber> {
+            OffchainCache::new(feed_id, T::CacheTtl::get(), T::CacheMaxStale::get())
+        }
+
+        fn parse_feed_price(
+            feed: &FeedConfig,
+            resp_bytes: &[u8],
+        ) -> Result<(u64, Permill), OffchainError> {
+            // 按照 feed 配置的 JSON pointer 取出价格字符串
+            let document = Self::parse_json(resp_bytes)?;
+            let value = feed.value_at(&document).ok_or_else(|| OffchainError::JsonShape {
+                path: feed.json_pointer.to_vec(),
+            })?;
+            let text = str::from_utf8(&value).map_err(|_| OffchainError::InvalidUtf8)?;
+
+            //折分成整部分和小数部分的元组
+            crate::price::parse_price(text, feed.decimals)
+                .map_err(|error| OffchainError::ParseNumber { value, error })
+        }
+
+        fn parse_json(resp_bytes: &[u8]) -> Result<serde_json::Value, OffchainError> {
+            let resp_str = str::from_utf8(resp_bytes).map_err(|_| OffchainError::InvalidUtf8)?;
+            serde_json::from_str(resp_str).map_err(|_| OffchainError::InvalidJson)
+        }
+
+        fn process_indexed_data(block_number: T::BlockNumber) -> Result<(), OffchainError> {
+            // 逐个处理未完成的请求，单个请求失败不影响其他请求。
+            let mut result = Ok(());
+            for (id, requested_at) in PendingIndexing::<T>::iter() {
+                if let Err(e) = Self::process_indexing_request(id, requested_at, block_number) {
+                    log::error!("...... process indexing request={} error={}", id, e);
+                    result = Err(e);
+                }
+            }
+            result
+        }
+
+        fn process_indexing_request(
+            id: u64,
+            requested_at: T::BlockNumber,
+            block_number: T::BlockNumber,
+        ) -> Result<(), OffchainError> {
+            let key = Self::indexing_key(requested_at, id);
+            // 结果提交后、上链前的几个区块内不再重复抓取；结果上链时该键随请求一起清除。
+            let submitted = Self::indexing_submitted_key(requested_at, id);
+            let s_submitted = StorageValueRef::persistent(&submitted);
+            if let Ok(Some(at)) = s_submitted.get::<T::BlockNumber>() {
+                if block_number < at + INDEXING_RESUBMIT_COOLDOWN.into() {
+                    return Ok(());
+                }
+            }
+
+            let IndexingData(url, _) = StorageValueRef::persistent(&key)
+                .get::<IndexingData>()
+                .ok()
+                .flatten()
+                .ok_or(OffchainError::IndexingDataMissing(id))?;
+            // `request_indexing` bounds the URL by `MaxUrlLength`, like the URL of a feed.
+            let feed = FeedConfig::http_get(&url, b"", 0)
+                .ok_or(OffchainError::IndexingDataMissing(id))?;
+            let body = Self::fetch_from_remote(&feed)?;
+            let result = IndexingResult {
+                length: body.len() as u32,
+                hash: sp_io::hashing::blake2_256(&body),
             };
+            log::info!("...... indexing request {}: {:?}", id, result);
 
-            Ok(dot_price)
+            let signer = Signer::<T, T::AuthorityId>::any_account();
+            let sent = signer.send_unsigned_transaction(
+                |acct| PayloadIndexing {
+                    id,
+                    result,
+                    public: acct.public.clone(),
+                },
+                Call::submit_indexing_result_unsigned_with_signed_payload,
+            );
+            match sent {
+                Some((_, Ok(()))) => {
+                    s_submitted.set(&block_number);
+                    Ok(())
+                }
+                Some((_, Err(()))) => {
+                    log::error!("...... Failed in process_indexing_request");
+                    Err(OffchainError::SubmitFailed)
+                }
+                None => {
+                    log::error!("...... No local account available");
+                    Err(OffchainError::NoLocalAccount)
+                }
+            }
         }
 
         fn append_or_replace_number(number: u64) {
             Numbers::<T>::mutate(|numbers| {
-                if numbers.len() == NUM_VEC_LEN {
-                    let _ = numbers.pop_front();
-                }
-                numbers.push_back(number);
+                push_latest(numbers, number);
 
                 log::info!("...... Number vector: {:?}", numbers);
             });
         }
 
-        fn fetch_github_info() -> Result<(), Error<T>> {
+        fn fetch_github_info(block_number: T::BlockNumber) -> Result<(), OffchainError> {
             log::info!("...... fetch_github_info! ");
 
-            let s_info = StorageValueRef::persistent(b"offchain-demo::gh-info");
-            if let Ok(Some(gh_info)) = s_info.get::<GithubInfo>() {
+            let now = sp_io::offchain::timestamp().unix_millis();
+            let cache = Self::feed_cache(GITHUB_FEED_ID);
+            if let Cached::Fresh(gh_info) = cache.lookup::<GithubInfo>(now) {
                 log::info!("...... cached gh-info: {:?}", gh_info);
                 return Ok(());
             }
@@ -512,64 +2290,184 @@ pub mod pallet {
             );
 
             if let Ok(_guard) = lock.try_lock() {
-                match Self::fetch_n_parse() {
-                    Ok(gh_info) => {
-                        s_info.set(&gh_info);
-                    }
-                    Err(err) => {
-                        return Err(err);
-                    }
-                }
+                // 缓存过期后重新获取，失败时仍可使用尚未过期太久的旧值。
+                let gh_info = cache.get_or_fetch(now, block_number, Self::fetch_n_parse)?;
+                log::info!("...... gh-info: {:?}", gh_info);
             }
             Ok(())
         }
 
-        fn fetch_n_parse() -> Result<GithubInfo, Error<T>> {
-            let resp_bytes = Self::fetch_from_remote(HTTP_REMOTE_REQUEST).map_err(|e| {
-                log::error!("...... fetch_from_remote error: {:?}", e);
-                <Error<T>>::HttpFetchingError
-            })?;
+        fn fetch_n_parse() -> Result<GithubInfo, OffchainError> {
+            let feed = Self::feed_id(GITHUB_FEED_ID)
+                .ok()
+                .and_then(Feeds::<T>::get)
+                .ok_or_else(|| OffchainError::FeedNotFound(GITHUB_FEED_ID.to_vec()))?;
+            let resp_bytes = Self::fetch_from_remote(&feed)?;
+
+            let v = Self::parse_json(&resp_bytes)?;
+            let gh_info = feed
+                .select(&v)
+                .and_then(|target| GithubInfo::deserialize(target).ok())
+                .ok_or_else(|| OffchainError::JsonShape { path: feed.json_pointer.to_vec() })?;
+            Ok(gh_info)
+        }
 
-            let resp_str =
-                str::from_utf8(&resp_bytes).map_err(|_| <Error<T>>::HttpFetchingError)?;
-            log::info!("{}", resp_str);
+        fn fetch_from_remote(feed: &FeedConfig) -> Result<Vec<u8>, OffchainError> {
+            let deadline = sp_io::offchain::timestamp()
+                .add(rt_offchain::Duration::from_millis(FETCH_TIMEOUT_PERIOD));
 
-            let gh_info: GithubInfo =
-                serde_json::from_str(&resp_str).map_err(|_| <Error<T>>::HttpFetchingError)?;
-            Ok(gh_info)
+            Self::fetch_bodies(&[feed], deadline)
+                .pop()
+                .unwrap_or(Err(OffchainError::Http(HttpFailure::Invalid)))
         }
 
-        fn fetch_from_remote(remote_request: &str) -> Result<Vec<u8>, Error<T>> {
-            log::info!("...... sending request to: {}", remote_request);
+        /// The response bodies of `feeds`, in order. The requests are in flight together and
+        /// retried following `HttpRetryPolicy` until `deadline`.
+        fn fetch_bodies(
+            feeds: &[&FeedConfig],
+            deadline: rt_offchain::Timestamp,
+        ) -> Vec<Result<Vec<u8>, OffchainError>> {
+            let policy = T::HttpRetryPolicy::get();
+            let now = sp_io::offchain::timestamp();
+
+            // 每个 feed 的结果，`None` 表示还需要请求；冷却中的 endpoint 不再请求。
+            let mut results: Vec<Option<Result<Vec<u8>, HttpFailure>>> = feeds
+                .iter()
+                .map(|feed| {
+                    if Self::endpoint_health(&feed.url).is_cooling_down(now.unix_millis()) {
+                        Some(Err(HttpFailure::CoolingDown))
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+
+            let mut attempt = 0;
+            loop {
+                attempt += 1;
+
+                // 先发出全部请求，再一起等待结果。
+                let mut sent = Vec::new();
+                let mut pending = Vec::new();
+                for (i, feed) in feeds.iter().enumerate() {
+                    if results[i].is_some() {
+                        continue;
+                    }
+                    match Self::send_request(feed, deadline) {
+                        Ok(request) => {
+                            sent.push(i);
+                            pending.push(request);
+                        }
+                        Err(failure) => results[i] = Some(Err(failure)),
+                    }
+                }
+                let responses = rt_offchain::http::PendingRequest::try_wait_all(pending, deadline);
+                for (i, response) in sent.into_iter().zip(responses) {
+                    let response = response.map_err(|_| HttpFailure::DeadlineReached);
+                    results[i] = Some(response.and_then(Self::read_response));
+                }
 
-            let request = rt_offchain::http::Request::get(remote_request);
+                // 可重试的失败在退避后重新请求，直到次数用完或等不到截止时间。
+                let retry: Vec<usize> = (0..feeds.len())
+                    .filter(|i| matches!(&results[*i], Some(Err(f)) if policy.should_retry(f)))
+                    .collect();
+                if retry.is_empty() || attempt >= policy.max_attempts {
+                    break;
+                }
+                let now = sp_io::offchain::timestamp();
+                let wake_up =
+                    now.add(rt_offchain::Duration::from_millis(policy.backoff_after(attempt)));
+                if wake_up >= deadline {
+                    break;
+                }
+                if wake_up > now {
+                    sp_io::offchain::sleep_until(wake_up);
+                }
+                for i in retry {
+                    log::info!("...... retrying request to {:?}", feeds[i].url);
+                    results[i] = None;
+                }
+            }
 
-            let timeout = sp_io::offchain::timestamp()
-                .add(rt_offchain::Duration::from_millis(FETCH_TIMEOUT_PERIOD));
+            let now = sp_io::offchain::timestamp().unix_millis();
+            feeds
+                .iter()
+                .zip(results)
+                .map(|(feed, result)| {
+                    let result = result.unwrap_or(Err(HttpFailure::DeadlineReached));
+                    Self::record_endpoint_result(&feed.url, &result, now, &policy);
+                    result.map_err(OffchainError::from)
+                })
+                .collect()
+        }
+
+        /// How the endpoint at `url` fared lately.
+        pub fn endpoint_health(url: &[u8]) -> EndpointHealth {
+            StorageValueRef::persistent(&endpoint_key(url))
+                .get()
+                .ok()
+                .flatten()
+                .unwrap_or_default()
+        }
+
+        fn record_endpoint_result(
+            url: &[u8],
+            result: &Result<Vec<u8>, HttpFailure>,
+            now: u64,
+            policy: &RetryPolicy,
+        ) {
+            let mut s_health = StorageValueRef::persistent(&endpoint_key(url));
+            match result {
+                Ok(_) => s_health.clear(),
+                Err(HttpFailure::CoolingDown) => {}
+                Err(failure) => {
+                    let mut health = Self::endpoint_health(url);
+                    health.record_failure(*failure, now, policy);
+                    s_health.set(&health);
+                }
+            }
+        }
+
+        fn send_request(
+            feed: &FeedConfig,
+            timeout: rt_offchain::Timestamp,
+        ) -> Result<rt_offchain::http::PendingRequest, HttpFailure> {
+            let url = str::from_utf8(&feed.url).map_err(|_| HttpFailure::Invalid)?;
+            log::info!("...... sending request to: {}", url);
+
+            let mut request = match feed.method {
+                HttpMethod::Get => rt_offchain::http::Request::get(url),
+                HttpMethod::Post => rt_offchain::http::Request::post(url, Vec::new()),
+            };
+            for (name, value) in feed.headers.iter() {
+                let name = str::from_utf8(name).map_err(|_| HttpFailure::Invalid)?;
+                let value = str::from_utf8(value).map_err(|_| HttpFailure::Invalid)?;
+                request = request.add_header(name, value);
+            }
 
-            let pending = request
-                .add_header("User-Agent", HTTP_HEADER_USER_AGENT)
+            request
                 .deadline(timeout) // Setting the timeout time
                 .send() // Sending the request out by the host
-                .map_err(|_| <Error<T>>::HttpFetchingError)?;
+                .map_err(HttpFailure::from)
+        }
 
-            let response = pending
-                .try_wait(timeout)
-                .map_err(|_| <Error<T>>::HttpFetchingError)?
-                .map_err(|_| <Error<T>>::HttpFetchingError)?;
+        fn read_response(
+            response: rt_offchain::http::HttpResult,
+        ) -> Result<Vec<u8>, HttpFailure> {
+            let response = response.map_err(HttpFailure::from)?;
 
             if response.code != 200 {
                 log::error!(
                     "...... Unexpected http request status code: {}",
                     response.code
                 );
-                return Err(<Error<T>>::HttpFetchingError);
+                return Err(HttpFailure::Status(response.code));
             }
 
             Ok(response.body().collect::<Vec<u8>>())
         }
 
-        fn offchain_signed_tx(block_number: T::BlockNumber) -> Result<(), Error<T>> {
+        fn offchain_signed_tx(block_number: T::BlockNumber) -> Result<(), OffchainError> {
             log::info!(
                 "...... offchain_signed_tx! block_number : {:?}",
                 block_number
@@ -580,36 +2478,38 @@ pub mod pallet {
             if let Some((acc, res)) = result {
                 if res.is_err() {
                     log::error!("...... failure: offchain_signed_tx: tx sent: {:?}", acc.id);
-                    return Err(<Error<T>>::OffchainSignedTxError);
+                    return Err(OffchainError::SubmitFailed);
                 }
                 return Ok(());
             }
             log::error!("...... No local account available");
-            Err(<Error<T>>::NoLocalAcctForSigning)
+            Err(OffchainError::NoLocalAccount)
         }
 
-        fn offchain_unsigned_tx(block_number: T::BlockNumber) -> Result<(), Error<T>> {
+        fn offchain_unsigned_tx(block_number: T::BlockNumber) -> Result<(), OffchainError> {
             log::info!(
                 "...... offchain_unsigned_tx! block_number : {:?}",
                 block_number
             );
+            Self::unsigned_due(block_number)?;
             let number: u64 = block_number.try_into().unwrap_or(0);
             let call = Call::submit_number_unsigned(number);
             SubmitTransaction::<T, Call<T>>::submit_unsigned_transaction(call.into()).map_err(
                 |_| {
                     log::error!("...... Failed in offchain_unsigned_tx");
-                    <Error<T>>::OffchainUnsignedTxError
+                    OffchainError::SubmitFailed
                 },
             )
         }
 
         fn offchain_unsigned_tx_signed_payload(
             block_number: T::BlockNumber,
-        ) -> Result<(), Error<T>> {
+        ) -> Result<(), OffchainError> {
             log::info!(
                 "...... offchain_unsigned_tx_signed_payload! block_number : {:?}",
                 block_number
             );
+            Self::unsigned_due(block_number)?;
             let signer = Signer::<T, T::AuthorityId>::any_account();
             let number: u64 = block_number.try_into().unwrap_or(0);
             let result = signer.send_unsigned_transaction(
@@ -622,11 +2522,41 @@ pub mod pallet {
             if let Some((_, res)) = result {
                 return res.map_err(|_| {
                     log::error!("...... Failed in offchain_unsigned_tx_signed_payload");
-                    <Error<T>>::OffchainUnsignedTxSignedPayloadError
+                    OffchainError::SubmitFailed
                 });
             }
             log::error!("...... No local account available");
-            Err(<Error<T>>::NoLocalAcctForSigning)
+            Err(OffchainError::NoLocalAccount)
+        }
+    }
+
+    impl<T: Config> AveragePrice for Pallet<T> {
+        fn time_weighted_average(symbol: &[u8]) -> Option<(u64, Permill)> {
+            Self::symbol_averages(symbol).map(|a| a.twap)
+        }
+
+        fn exponential_moving_average(symbol: &[u8]) -> Option<(u64, Permill)> {
+            Self::symbol_averages(symbol).map(|a| a.ema)
+        }
+    }
+
+    impl<T: Config> LatestPrice for Pallet<T> {
+        fn latest_timestamped_price(symbol: &[u8]) -> Option<((u64, Permill), u64)> {
+            Self::timestamped_prices(symbol).pop()
+        }
+
+        fn fresh_price(symbol: &[u8]) -> Option<(u64, Permill)> {
+            let bounded = SymbolOf::<T>::try_from(symbol.to_vec()).ok()?;
+            if Quarantined::<T>::contains_key(&bounded) {
+                return None;
+            }
+            let (price, recorded_at) = Self::latest_timestamped_price(symbol)?;
+            let max_age = Self::price_guards(&bounded).map_or(0, |guard| guard.max_age);
+            let now = T::UnixTime::now().as_millis() as u64;
+            if max_age != 0 && now.saturating_sub(recorded_at) > max_age {
+                return None;
+            }
+            Some(price)
         }
     }
 
@@ -637,4 +2567,10 @@ pub mod pallet {
             <frame_system::Pallet<T>>::block_number()
         }
     }
+
+    /// Lets the runtime list the `demo` key in its session keys, so `author_rotateKeys` creates
+    /// one for the offchain worker.
+    impl<T: Config> BoundToRuntimeAppPublic for Pallet<T> {
+        type Public = crypto::Public;
+    }
 }
@@ -0,0 +1,135 @@
+//! Storage migrations of the pallet.
+
+/// Version 1 keeps a bounded, timestamped price history for every tracked asset, and bounds
+/// `Numbers` by `Config::MaxHistoryLength`.
+///
+/// Version 0 is the layout the pallet started with: `Numbers` a `VecDeque<u64>`, and `Prices` a
+/// single `VecDeque<(u64, Permill)>` of DOT/USD prices. `Numbers` encodes like the `BoundedVec`
+/// replacing it. The DOT prices move to the `DOT` entry of the `Prices` map, whose entries live
+/// under the key of the old value, stamped with the time of the upgrade as no earlier one is
+/// known. Only the oldest values of a history longer than the new bound are dropped, and the
+/// averages over the history are computed as if its prices had been recorded one by one.
+///
+/// Version 0 fetched the DOT price from coincap whatever the storage held, so DOT is tracked with
+/// that feed unless it already is; the offchain worker keeps pricing it after the upgrade.
+pub mod v1 {
+	use crate::{
+		feed::FeedConfig,
+		pallet::latest,
+		AssetInfo, Averages, Config, FeedIdOf, Feeds, Numbers, Pallet, Prices, SymbolOf,
+		TimestampedPrice, TrackedAssets,
+	};
+	use core::convert::{TryFrom, TryInto};
+	use frame_support::{
+		storage::{unhashed, StoragePrefixedMap},
+		traits::{Get, GetStorageVersion, StorageVersion, UnixTime},
+		weights::Weight,
+	};
+	use sp_arithmetic::per_things::Permill;
+	use sp_std::{collections::vec_deque::VecDeque, prelude::*};
+
+	/// Symbol of the asset version 0 recorded the prices of.
+	pub const V0_SYMBOL: &[u8] = b"DOT";
+
+	/// Id of the feed of the DOT price version 0 fetched.
+	pub const V0_FEED_ID: &[u8] = b"polkadot";
+
+	/// The coincap endpoint version 0 fetched the DOT price from.
+	fn v0_feed() -> FeedConfig {
+		FeedConfig::http_get(b"https://api.coincap.io/v2/assets/polkadot", b"/data/priceUsd", 0)
+			.expect("the feed of version 0 fits the bounds of a feed")
+	}
+
+	/// The version 0 `Prices` value, if it is still in storage.
+	fn v0_prices<T: Config>() -> Option<VecDeque<(u64, Permill)>> {
+		unhashed::get(&Prices::<T>::final_prefix())
+	}
+
+	/// Move the storage from version 0 to version 1, if it is still at version 0.
+	pub fn migrate<T: Config>() -> Weight {
+		if Pallet::<T>::on_chain_storage_version() >= StorageVersion::new(1) {
+			log::info!("...... pallet_ocw storage is already at version 1");
+			return T::DbWeight::get().reads(1);
+		}
+
+		let _ = Numbers::<T>::translate::<Vec<u64>, _>(|numbers| numbers.map(latest));
+		let symbol = SymbolOf::<T>::try_from(V0_SYMBOL.to_vec())
+			.expect("`MaxSymbolLength` fits the symbol of version 0");
+		let (mut reads, mut writes) = (4, 2);
+		if !TrackedAssets::<T>::contains_key(&symbol) {
+			let feed_id = FeedIdOf::<T>::try_from(V0_FEED_ID.to_vec())
+				.expect("`MaxFeedIdLength` fits the feed id of version 0");
+			if !Feeds::<T>::contains_key(&feed_id) {
+				Feeds::<T>::insert(&feed_id, v0_feed());
+				writes += 1;
+			}
+			let feeds = vec![feed_id].try_into().expect("`MaxFeedsPerAsset` fits one feed");
+			TrackedAssets::<T>::insert(&symbol, AssetInfo { feeds });
+			reads += 1;
+			writes += 1;
+		}
+		if let Some(prices) = v0_prices::<T>() {
+			unhashed::kill(&Prices::<T>::final_prefix());
+			let timestamp = T::UnixTime::now().as_millis() as u64;
+			let history = prices
+				.into_iter()
+				.map(|price| TimestampedPrice { price, timestamp })
+				.collect::<Vec<_>>();
+			log::info!("...... pallet_ocw migrating {} prices of DOT", history.len());
+			let history = latest::<_, T::MaxHistoryLength>(history);
+			if let Some(averages) = Pallet::<T>::averages_of(&history) {
+				Averages::<T>::insert(&symbol, averages);
+				writes += 1;
+			}
+			Prices::<T>::insert(&symbol, history);
+			reads += 1;
+			writes += 2;
+		}
+		StorageVersion::new(1).put::<Pallet<T>>();
+
+		log::info!("...... pallet_ocw storage migrated to version 1");
+		T::DbWeight::get().reads_writes(reads, writes)
+	}
+
+	#[cfg(feature = "try-runtime")]
+	pub fn pre_upgrade<T: Config>() -> Result<(), &'static str> {
+		use frame_support::traits::OnRuntimeUpgradeHelpersExt;
+
+		if Pallet::<T>::on_chain_storage_version() >= StorageVersion::new(1) {
+			return Ok(());
+		}
+		let numbers: Vec<u64> = unhashed::get(&Numbers::<T>::hashed_key()).unwrap_or_default();
+		Pallet::<T>::set_temp_storage(numbers.last().copied(), "latest_number");
+		let latest_price = v0_prices::<T>().and_then(|prices| prices.back().copied());
+		Pallet::<T>::set_temp_storage(latest_price, "latest_price");
+		Ok(())
+	}
+
+	#[cfg(feature = "try-runtime")]
+	pub fn post_upgrade<T: Config>() -> Result<(), &'static str> {
+		use frame_support::traits::OnRuntimeUpgradeHelpersExt;
+
+		if Pallet::<T>::on_chain_storage_version() != StorageVersion::new(1) {
+			return Err("pallet_ocw storage is not at version 1");
+		}
+		if v0_prices::<T>().is_some() {
+			return Err("the version 0 prices are still in storage");
+		}
+		let symbol = SymbolOf::<T>::try_from(V0_SYMBOL.to_vec()).map_err(|_| "symbol too long")?;
+		if !TrackedAssets::<T>::contains_key(&symbol) {
+			return Err("DOT is not tracked");
+		}
+		if let Some(latest_number) = Pallet::<T>::get_temp_storage::<Option<u64>>("latest_number") {
+			if Numbers::<T>::get().last().copied() != latest_number {
+				return Err("the latest number was not kept");
+			}
+		}
+		let latest_price = Pallet::<T>::get_temp_storage::<Option<(u64, Permill)>>("latest_price");
+		if let Some(latest_price) = latest_price {
+			if Pallet::<T>::latest_price(V0_SYMBOL) != latest_price {
+				return Err("the latest DOT price was not kept");
+			}
+		}
+		Ok(())
+	}
+}
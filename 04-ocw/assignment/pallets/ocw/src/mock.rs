@@ -0,0 +1,236 @@
+use crate as pallet_ocw;
+use crate::retry::RetryPolicy;
+use frame_support::{parameter_types, traits::GenesisBuild, PalletId};
+use parking_lot::RwLock;
+use sp_arithmetic::per_things::Permill;
+use sp_core::{
+	offchain::{
+		testing::{self, OffchainState, PoolState},
+		OffchainDbExt, OffchainWorkerExt, TransactionPoolExt,
+	},
+	sr25519::{self, Signature},
+	H256,
+};
+use sp_keystore::{testing::KeyStore, KeystoreExt, SyncCryptoStore};
+use sp_runtime::{
+	testing::{Header, TestXt},
+	traits::{BlakeTwo256, Extrinsic as ExtrinsicT, IdentifyAccount, IdentityLookup, Verify},
+};
+use std::sync::Arc;
+
+type UncheckedExtrinsic = frame_system::mocking::MockUncheckedExtrinsic<Test>;
+type Block = frame_system::mocking::MockBlock<Test>;
+
+// Configure a mock runtime to test the pallet.
+frame_support::construct_runtime!(
+	pub enum Test where
+		Block = Block,
+		NodeBlock = Block,
+		UncheckedExtrinsic = UncheckedExtrinsic,
+	{
+		System: frame_system::{Pallet, Call, Config, Storage, Event<T>},
+		Timestamp: pallet_timestamp::{Pallet, Call, Storage, Inherent},
+		Balances: pallet_balances::{Pallet, Call, Storage, Config<T>, Event<T>},
+		OcwDemo: pallet_ocw::{Pallet, Call, Config<T>, Storage, Event<T>, ValidateUnsigned},
+	}
+);
+
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+	pub const SS58Prefix: u8 = 42;
+	pub const MinimumPeriod: u64 = 1;
+}
+
+impl frame_system::Config for Test {
+	type BaseCallFilter = frame_support::traits::AllowAll;
+	type BlockWeights = ();
+	type BlockLength = ();
+	type DbWeight = ();
+	type Origin = Origin;
+	type Call = Call;
+	type Index = u64;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = AccountId;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type Event = Event;
+	type BlockHashCount = BlockHashCount;
+	type Version = ();
+	type PalletInfo = PalletInfo;
+	type AccountData = pallet_balances::AccountData<u64>;
+	type OnNewAccount = ();
+	type OnKilledAccount = ();
+	type SystemWeightInfo = ();
+	type SS58Prefix = SS58Prefix;
+	type OnSetCode = ();
+}
+
+impl pallet_timestamp::Config for Test {
+	type Moment = u64;
+	type OnTimestampSet = ();
+	type MinimumPeriod = MinimumPeriod;
+	type WeightInfo = ();
+}
+
+parameter_types! {
+	pub const ExistentialDeposit: u64 = 1;
+}
+
+impl pallet_balances::Config for Test {
+	type MaxLocks = ();
+	type MaxReserves = ();
+	type ReserveIdentifier = [u8; 8];
+	type Balance = u64;
+	type Event = Event;
+	type DustRemoval = ();
+	type ExistentialDeposit = ExistentialDeposit;
+	type AccountStore = System;
+	type WeightInfo = ();
+}
+
+pub type Extrinsic = TestXt<Call, ()>;
+pub type AccountId = <<Signature as Verify>::Signer as IdentifyAccount>::AccountId;
+
+impl frame_system::offchain::SigningTypes for Test {
+	type Public = <Signature as Verify>::Signer;
+	type Signature = Signature;
+}
+
+impl<LocalCall> frame_system::offchain::SendTransactionTypes<LocalCall> for Test
+where
+	Call: From<LocalCall>,
+{
+	type OverarchingCall = Call;
+	type Extrinsic = Extrinsic;
+}
+
+impl<LocalCall> frame_system::offchain::CreateSignedTransaction<LocalCall> for Test
+where
+	Call: From<LocalCall>,
+{
+	fn create_transaction<C: frame_system::offchain::AppCrypto<Self::Public, Self::Signature>>(
+		call: Call,
+		_public: <Signature as Verify>::Signer,
+		_account: AccountId,
+		nonce: u64,
+	) -> Option<(Call, <Extrinsic as ExtrinsicT>::SignaturePayload)> {
+		Some((call, (nonce, ())))
+	}
+}
+
+parameter_types! {
+	pub const OcwPalletId: PalletId = PalletId(*b"py/ocwdm");
+	pub const ReporterReward: u64 = 10;
+	pub const MinReporterBond: u64 = 100;
+	pub ReportTolerance: Permill = Permill::from_percent(5);
+	pub DeviationSlash: Permill = Permill::from_percent(10);
+	pub const MaxOracles: u32 = 4;
+	pub const MaxPendingIndexing: u32 = 2;
+	pub const MaxMissedRounds: u32 = 2;
+	pub const SuspensionPeriod: u64 = 10;
+	pub const CommitPeriod: u64 = 2;
+	pub const RevealPeriod: u64 = 2;
+	pub const MaxSymbolLength: u32 = 8;
+	pub const MaxFeedIdLength: u32 = 16;
+	pub const MaxFeedsPerAsset: u32 = 3;
+	pub MaxPriceDeviation: Permill = Permill::from_percent(10);
+	pub const MinPriceSources: u32 = 1;
+	pub const UnsignedInterval: u64 = 2;
+	pub const MaxHistoryLength: u32 = 10;
+	pub const OffchainTimeBudget: u64 = 2_000;
+	pub const CacheTtl: u64 = 60_000;
+	pub const CacheMaxStale: u64 = 60_000;
+	pub HttpRetryPolicy: RetryPolicy = RetryPolicy {
+		max_attempts: 3,
+		backoff: 0,
+		retry_statuses: vec![429, 503],
+		failure_threshold: 2,
+		cooldown: 30_000,
+	};
+	pub EmaSmoothing: Permill = Permill::from_percent(50);
+}
+
+impl pallet_ocw::Config for Test {
+	type Event = Event;
+	type Call = Call;
+	type AuthorityId = pallet_ocw::crypto::TestAuthId;
+	type Currency = Balances;
+	type PalletId = OcwPalletId;
+	type ReporterReward = ReporterReward;
+	type MinReporterBond = MinReporterBond;
+	type ReportTolerance = ReportTolerance;
+	type DeviationSlash = DeviationSlash;
+	type MaxOracles = MaxOracles;
+	type MaxPendingIndexing = MaxPendingIndexing;
+	type MaxMissedRounds = MaxMissedRounds;
+	type SuspensionPeriod = SuspensionPeriod;
+	type CommitPeriod = CommitPeriod;
+	type RevealPeriod = RevealPeriod;
+	type MaxSymbolLength = MaxSymbolLength;
+	type MaxFeedIdLength = MaxFeedIdLength;
+	type MaxFeedsPerAsset = MaxFeedsPerAsset;
+	type MaxPriceDeviation = MaxPriceDeviation;
+	type MinPriceSources = MinPriceSources;
+	type UnixTime = Timestamp;
+	type EmaSmoothing = EmaSmoothing;
+	type CacheTtl = CacheTtl;
+	type CacheMaxStale = CacheMaxStale;
+	type HttpRetryPolicy = HttpRetryPolicy;
+	type OffchainTimeBudget = OffchainTimeBudget;
+	type UnsignedInterval = UnsignedInterval;
+	type MaxHistoryLength = MaxHistoryLength;
+	type WeightInfo = ();
+}
+
+/// Seed of the `demo` key the offchain worker signs with in tests.
+pub const PHRASE: &str = "news slush supreme milk chapter athlete soap sausage put clutch what kitten";
+
+/// Balance the oracle is endowed with at genesis, `MinReporterBond` of it bonded.
+pub const ORACLE_BALANCE: u64 = 1_000;
+
+/// Handles into the offchain test doubles, used to script HTTP responses and inspect the pool.
+pub struct OffchainHandles {
+	pub offchain: Arc<RwLock<OffchainState>>,
+	pub pool: Arc<RwLock<PoolState>>,
+	pub keystore: Arc<KeyStore>,
+	pub public: sr25519::Public,
+}
+
+// Build genesis storage according to the mock runtime.
+pub fn new_test_ext() -> sp_io::TestExternalities {
+	frame_system::GenesisConfig::default().build_storage::<Test>().unwrap().into()
+}
+
+// Build externalities with an offchain worker, a transaction pool and a keystore holding one
+// `demo` key, registered and bonded as an oracle at genesis.
+pub fn new_offchain_test_ext() -> (sp_io::TestExternalities, OffchainHandles) {
+	let (offchain, offchain_state) = testing::TestOffchainExt::new();
+	let (pool, pool_state) = testing::TestTransactionPoolExt::new();
+
+	let keystore = Arc::new(KeyStore::new());
+	let public = SyncCryptoStore::sr25519_generate_new(
+		&*keystore,
+		pallet_ocw::KEY_TYPE,
+		Some(&format!("{}/hunter1", PHRASE)),
+	)
+	.unwrap();
+
+	let mut storage = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	pallet_balances::GenesisConfig::<Test> { balances: vec![(public, ORACLE_BALANCE)] }
+		.assimilate_storage(&mut storage)
+		.unwrap();
+	pallet_ocw::GenesisConfig::<Test> { oracles: vec![public], ..Default::default() }
+		.assimilate_storage(&mut storage)
+		.unwrap();
+
+	let mut t: sp_io::TestExternalities = storage.into();
+	t.register_extension(OffchainDbExt::new(offchain.clone()));
+	t.register_extension(OffchainWorkerExt::new(offchain));
+	t.register_extension(TransactionPoolExt::new(pool));
+	t.register_extension(KeystoreExt(keystore.clone()));
+	t.execute_with(|| System::set_block_number(1));
+
+	(t, OffchainHandles { offchain: offchain_state, pool: pool_state, keystore, public })
+}
@@ -0,0 +1,131 @@
+//! Conversion of decimal price strings into the fixed-point representation stored on chain.
+//!
+//! Prices are kept as `(u64, Permill)`: the integer part and the fraction in millionths. Remote
+//! feeds hand out arbitrary decimal strings (`"7"`, `"28.1234567890"`, `"1.5e-3"`), so parsing
+//! never panics and reports a typed [`ParseError`] instead.
+
+use core::convert::TryFrom;
+use sp_arithmetic::per_things::Permill;
+use sp_runtime::RuntimeDebug;
+
+/// Number of fractional digits a price carries, i.e. the precision of `Permill`.
+pub const PRICE_DECIMALS: u32 = 6;
+
+/// `10^PRICE_DECIMALS`.
+const PRICE_UNIT: u128 = 1_000_000;
+
+/// Exponents are clamped to this magnitude, far beyond anything representable in a `u128`.
+const MAX_EXPONENT: i64 = 100_000;
+
+/// Why a decimal string could not be turned into a price.
+#[derive(Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum ParseError {
+	/// The input contains no digits.
+	Empty,
+	/// The input is not a decimal number, e.g. stray characters or a missing exponent.
+	InvalidFormat,
+	/// The input is a negative, non-zero number.
+	Negative,
+	/// The value does not fit in the target type.
+	Overflow,
+}
+
+/// Parse a decimal string and return its value multiplied by `10^scale`, rounded half up.
+///
+/// Accepts an optional sign, any number of integer and fractional digits (leading zeros included)
+/// and an optional `e`/`E` exponent. A negative `scale` divides instead of multiplying.
+pub fn parse_scaled(input: &str, scale: i32) -> Result<u128, ParseError> {
+	let bytes = input.trim().as_bytes();
+	let (negative, rest) = match bytes.split_first() {
+		Some((b'-', rest)) => (true, rest),
+		Some((b'+', rest)) => (false, rest),
+		Some(_) => (false, bytes),
+		None => return Err(ParseError::Empty),
+	};
+
+	let (mantissa, exponent) = match rest.iter().position(|b| *b == b'e' || *b == b'E') {
+		Some(i) => (&rest[..i], parse_exponent(&rest[i + 1..])?),
+		None => (rest, 0),
+	};
+	let (int_digits, frac_digits) = match mantissa.iter().position(|b| *b == b'.') {
+		Some(i) => (&mantissa[..i], &mantissa[i + 1..]),
+		None => (mantissa, &mantissa[mantissa.len()..]),
+	};
+	if int_digits.is_empty() && frac_digits.is_empty() {
+		return Err(if exponent == 0 && rest.is_empty() {
+			ParseError::Empty
+		} else {
+			ParseError::InvalidFormat
+		});
+	}
+	if !int_digits.iter().chain(frac_digits).all(u8::is_ascii_digit) {
+		return Err(ParseError::InvalidFormat);
+	}
+
+	// Work on the significant digits only: the value is `0.d1 d2 .. dn * 10^point`.
+	let total = int_digits.len() + frac_digits.len();
+	let digit_at = |i: usize| -> u8 {
+		if i < int_digits.len() {
+			int_digits[i] - b'0'
+		} else {
+			frac_digits[i - int_digits.len()] - b'0'
+		}
+	};
+	let leading_zeros = (0..total).take_while(|i| digit_at(*i) == 0).count();
+	let significant = total - leading_zeros;
+	if significant == 0 {
+		return Ok(0);
+	}
+	if negative {
+		return Err(ParseError::Negative);
+	}
+
+	let point = int_digits.len() as i64 - leading_zeros as i64 + exponent + scale as i64;
+	if point < 0 {
+		// Even the first significant digit sits below the rounding position.
+		return Ok(0);
+	}
+	// `u128::MAX` has 39 digits.
+	if point > 39 {
+		return Err(ParseError::Overflow);
+	}
+
+	let point = point as usize;
+	let significant_at = |i: usize| if i < significant { digit_at(leading_zeros + i) } else { 0 };
+	let mut value: u128 = 0;
+	for i in 0..point {
+		value = value
+			.checked_mul(10)
+			.and_then(|v| v.checked_add(significant_at(i) as u128))
+			.ok_or(ParseError::Overflow)?;
+	}
+	if significant_at(point) >= 5 {
+		value = value.checked_add(1).ok_or(ParseError::Overflow)?;
+	}
+	Ok(value)
+}
+
+/// Parse a price string into `(integer, fraction)`.
+///
+/// `decimals` is the number of implied decimals of the raw value, e.g. `"712345"` with
+/// `decimals = 5` is `7.12345`. Plain decimal strings use `decimals = 0`.
+pub fn parse_price(input: &str, decimals: u8) -> Result<(u64, Permill), ParseError> {
+	let scaled = parse_scaled(input, PRICE_DECIMALS as i32 - decimals as i32)?;
+	let integer = u64::try_from(scaled / PRICE_UNIT).map_err(|_| ParseError::Overflow)?;
+	Ok((integer, Permill::from_parts((scaled % PRICE_UNIT) as u32)))
+}
+
+fn parse_exponent(bytes: &[u8]) -> Result<i64, ParseError> {
+	let (negative, digits) = match bytes.split_first() {
+		Some((b'-', rest)) => (true, rest),
+		Some((b'+', rest)) => (false, rest),
+		_ => (false, bytes),
+	};
+	if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+		return Err(ParseError::InvalidFormat);
+	}
+	let magnitude = digits
+		.iter()
+		.fold(0i64, |acc, d| (acc * 10 + (d - b'0') as i64).min(MAX_EXPONENT));
+	Ok(if negative { -magnitude } else { magnitude })
+}
@@ -0,0 +1,114 @@
+//! Retrying failed HTTP requests, and backing off from endpoints that keep failing.
+//!
+//! How every endpoint fared lately is kept in persistent offchain storage as an
+//! [`EndpointHealth`], so the offchain worker skips an endpoint for a while after it failed
+//! `failure_threshold` times in a row.
+
+use parity_scale_codec::{Decode, Encode};
+use sp_runtime::{
+	offchain::{http, HttpError},
+	RuntimeDebug,
+};
+use sp_std::prelude::*;
+
+/// Prefix of the persistent offchain storage keys of the endpoint health records.
+pub const ENDPOINT_PREFIX: &[u8] = b"offchain-demo::endpoint::";
+
+/// Persistent offchain storage key of the health record of the endpoint at `url`.
+pub fn endpoint_key(url: &[u8]) -> Vec<u8> {
+	[ENDPOINT_PREFIX, url].concat()
+}
+
+/// Why an HTTP request failed.
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+pub enum HttpFailure {
+	/// No response before the deadline.
+	DeadlineReached,
+	/// The connection failed.
+	IoError,
+	/// The request was malformed or unknown to the host.
+	Invalid,
+	/// The server answered with a status code other than 200.
+	Status(u16),
+	/// The endpoint failed too often lately and was not queried.
+	CoolingDown,
+}
+
+impl From<HttpError> for HttpFailure {
+	fn from(e: HttpError) -> Self {
+		match e {
+			HttpError::DeadlineReached => HttpFailure::DeadlineReached,
+			HttpError::IoError => HttpFailure::IoError,
+			HttpError::Invalid => HttpFailure::Invalid,
+		}
+	}
+}
+
+impl From<http::Error> for HttpFailure {
+	fn from(e: http::Error) -> Self {
+		match e {
+			http::Error::DeadlineReached => HttpFailure::DeadlineReached,
+			http::Error::IoError => HttpFailure::IoError,
+			http::Error::Unknown => HttpFailure::Invalid,
+		}
+	}
+}
+
+/// How failed HTTP requests are retried, and when an endpoint is given a rest.
+#[derive(Encode, Decode, Clone, PartialEq, Eq, RuntimeDebug)]
+pub struct RetryPolicy {
+	/// Attempts per request and run, the first one included.
+	pub max_attempts: u32,
+	/// Milliseconds to wait before the second attempt, doubled before every later one.
+	pub backoff: u64,
+	/// Status codes worth another attempt, e.g. `429` and `503`.
+	pub retry_statuses: Vec<u16>,
+	/// Failed runs in a row after which an endpoint cools down.
+	pub failure_threshold: u32,
+	/// Milliseconds an endpoint is skipped once it cools down.
+	pub cooldown: u64,
+}
+
+impl RetryPolicy {
+	/// Whether a request that failed with `failure` is attempted again.
+	pub fn should_retry(&self, failure: &HttpFailure) -> bool {
+		match failure {
+			HttpFailure::IoError => true,
+			HttpFailure::Status(code) => self.retry_statuses.contains(code),
+			_ => false,
+		}
+	}
+
+	/// Milliseconds to wait after the failed `attempt`, counted from 1.
+	pub fn backoff_after(&self, attempt: u32) -> u64 {
+		let factor = 1u64.checked_shl(attempt.saturating_sub(1)).unwrap_or(u64::MAX);
+		self.backoff.saturating_mul(factor)
+	}
+}
+
+/// How an endpoint fared lately.
+#[derive(Encode, Decode, Clone, Default, PartialEq, Eq, RuntimeDebug)]
+pub struct EndpointHealth {
+	/// Runs in a row in which the endpoint failed.
+	pub consecutive_failures: u32,
+	/// Why the endpoint failed last.
+	pub last_error: Option<HttpFailure>,
+	/// Unix time in milliseconds until which the endpoint is skipped.
+	pub cooldown_until: u64,
+}
+
+impl EndpointHealth {
+	/// Whether the endpoint is skipped at `now`, in Unix milliseconds.
+	pub fn is_cooling_down(&self, now: u64) -> bool {
+		now < self.cooldown_until
+	}
+
+	/// Record a run failed with `failure` at `now`, starting a cooldown once `policy` says so.
+	pub fn record_failure(&mut self, failure: HttpFailure, now: u64, policy: &RetryPolicy) {
+		self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+		self.last_error = Some(failure);
+		if self.consecutive_failures >= policy.failure_threshold {
+			self.cooldown_until = now.saturating_add(policy.cooldown);
+		}
+	}
+}
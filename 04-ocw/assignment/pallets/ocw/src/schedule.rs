@@ -0,0 +1,84 @@
+//! When the offchain worker runs each of its tasks.
+//!
+//! Every [`OffchainTask`] runs on the blocks its [`TaskSchedule`] marks as due. Root can change a
+//! schedule on-chain; tasks without one follow [`TaskSchedule::default_for`].
+
+use parity_scale_codec::{Decode, Encode, MaxEncodedLen};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
+use sp_runtime::{
+	traits::{AtLeast32BitUnsigned, Zero},
+	RuntimeDebug,
+};
+
+/// Interval of the default schedules, which run the block number and data tasks one per block in
+/// turn.
+pub const DEFAULT_TASK_INTERVAL: u32 = 5;
+
+/// A job of the offchain worker.
+#[derive(Encode, Decode, MaxEncodedLen, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum OffchainTask {
+	/// Submit the block number in a signed transaction.
+	SignedNumber,
+	/// Submit the block number in an unsigned transaction.
+	UnsignedNumber,
+	/// Submit the block number in an unsigned transaction with a signed payload.
+	SignedPayloadNumber,
+	/// Fetch and cache the GitHub organization info.
+	GithubInfo,
+	/// Fetch and submit the prices of the tracked assets.
+	Prices,
+	/// Reveal the prices committed to in commit–reveal mode once their reveal window opens.
+	RevealPrices,
+	/// Fetch the URLs of the pending indexing requests and submit the results.
+	IndexedData,
+}
+
+impl OffchainTask {
+	/// Every task, in the order due tasks run within a block.
+	pub const ALL: [OffchainTask; 7] = [
+		OffchainTask::SignedNumber,
+		OffchainTask::UnsignedNumber,
+		OffchainTask::SignedPayloadNumber,
+		OffchainTask::GithubInfo,
+		OffchainTask::Prices,
+		OffchainTask::RevealPrices,
+		OffchainTask::IndexedData,
+	];
+}
+
+/// Runs a task on every block `n` with `n % interval == offset % interval`, unless paused.
+#[derive(Encode, Decode, MaxEncodedLen, Clone, Copy, PartialEq, Eq, RuntimeDebug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct TaskSchedule<BlockNumber> {
+	pub interval: BlockNumber,
+	pub offset: BlockNumber,
+	/// Whether the task runs at all.
+	pub enabled: bool,
+}
+
+impl<BlockNumber: AtLeast32BitUnsigned + Copy> TaskSchedule<BlockNumber> {
+	/// The schedule of `task` until root sets one: every `DEFAULT_TASK_INTERVAL` blocks, one task
+	/// per block. Indexing requests are processed and committed prices revealed every block, as
+	/// they wait on it.
+	pub fn default_for(task: OffchainTask) -> Self {
+		let (interval, offset): (u32, u32) = match task {
+			OffchainTask::Prices => (DEFAULT_TASK_INTERVAL, 0),
+			OffchainTask::SignedNumber => (DEFAULT_TASK_INTERVAL, 1),
+			OffchainTask::UnsignedNumber => (DEFAULT_TASK_INTERVAL, 2),
+			OffchainTask::SignedPayloadNumber => (DEFAULT_TASK_INTERVAL, 3),
+			OffchainTask::GithubInfo => (DEFAULT_TASK_INTERVAL, 4),
+			OffchainTask::RevealPrices => (1, 0),
+			OffchainTask::IndexedData => (1, 0),
+		};
+		TaskSchedule { interval: interval.into(), offset: offset.into(), enabled: true }
+	}
+
+	/// Whether the task runs at `block_number`.
+	pub fn is_due(&self, block_number: BlockNumber) -> bool {
+		self.enabled &&
+			!self.interval.is_zero() &&
+			block_number % self.interval == self.offset % self.interval
+	}
+}
@@ -0,0 +1,2279 @@
+use crate::{
+	aggregate::{aggregate, deviates, ema, mean, median, time_weighted_mean},
+	cache::{CacheEntry, Cached, Freshness, OffchainCache},
+	commit::{phase, Phase, SubmissionMode, REVEALS_KEY},
+	error::OffchainError,
+	feed::{FeedConfig, Header, HttpMethod, MaxHeaders, MaxUrlLength},
+	mock::*,
+	price::{parse_price, parse_scaled, ParseError},
+	retry::{endpoint_key, EndpointHealth, HttpFailure, RetryPolicy},
+	schedule::{OffchainTask, TaskSchedule},
+	traits::{AveragePrice, LatestPrice},
+	AssetInfo, Call as OcwCall, Error, FeedIdOf, IndexingData, IndexingResult, NextUnsignedAt,
+	Numbers, OracleCount, Oracles, Payload, PayloadCommit, PayloadIndexing, PayloadPrice,
+	PayloadReveal, PendingRevealsOf, PriceAverages, PriceGuard, Prices, SymbolOf, TimestampedPrice,
+	TrackedAssets, ValidityError, GITHUB_FEED_ID,
+};
+use core::convert::TryInto;
+use frame_support::{
+	assert_noop, assert_ok,
+	dispatch::DispatchResult,
+	storage::{unhashed, StoragePrefixedMap},
+	traits::{
+		Currency, GenesisBuild, Get, GetStorageVersion, OffchainWorker, OnInitialize,
+		StorageVersion,
+	},
+	BoundedVec,
+};
+use frame_system::offchain::{SignedPayload, SigningTypes};
+use parity_scale_codec::{Decode, Encode};
+use parking_lot::RwLock;
+use proptest::prelude::*;
+use sp_arithmetic::per_things::Permill;
+use sp_core::offchain::{testing::OffchainState, testing::PendingRequest, StorageKind, Timestamp};
+use sp_keystore::SyncCryptoStore;
+use sp_runtime::{
+	offchain::storage::StorageValueRef,
+	traits::{BadOrigin, ValidateUnsigned},
+	transaction_validity::{InvalidTransaction, TransactionSource},
+};
+use std::{collections::VecDeque, sync::Arc};
+
+const COINCAP_DOT: &str = "https://api.coincap.io/v2/assets/polkadot";
+const COINCAP_KSM: &str = "https://api.coincap.io/v2/assets/kusama";
+const GITHUB_ORG: &str = "https://api.github.com/orgs/substrate-developer-hub";
+const COINCAP_DOT_RESPONSE: &[u8] =
+	br#"{"data":{"id":"polkadot","symbol":"DOT","name":"Polkadot","priceUsd":"28.1234567890"},"timestamp":1633660000000}"#;
+const COINCAP_KSM_RESPONSE: &[u8] =
+	br#"{"data":{"id":"kusama","symbol":"KSM","name":"Kusama","priceUsd":"301.5"},"timestamp":1633660000000}"#;
+const GITHUB_ORG_RESPONSE: &[u8] =
+	br#"{"login":"substrate-developer-hub","blog":"https://substrate.dev","public_repos":42}"#;
+
+type Public = <Test as SigningTypes>::Public;
+
+fn expect_get(state: &Arc<RwLock<OffchainState>>, uri: &str, response: &[u8]) {
+	state.write().expect_request(PendingRequest {
+		method: "GET".into(),
+		uri: uri.into(),
+		headers: vec![("User-Agent".into(), "jimmychu0807".into())],
+		response: Some(response.to_vec()),
+		sent: true,
+		..Default::default()
+	});
+}
+
+fn symbol(s: &[u8]) -> SymbolOf<Test> {
+	s.to_vec().try_into().unwrap()
+}
+
+fn feed_id(s: &[u8]) -> FeedIdOf<Test> {
+	s.to_vec().try_into().unwrap()
+}
+
+fn bounded<S: Get<u32>>(bytes: &[u8]) -> BoundedVec<u8, S> {
+	bytes.to_vec().try_into().unwrap()
+}
+
+fn headers(headers: &[(&[u8], &[u8])]) -> BoundedVec<Header, MaxHeaders> {
+	let headers: Vec<Header> =
+		headers.iter().map(|(name, value)| (bounded(name), bounded(value))).collect();
+	headers.try_into().unwrap()
+}
+
+fn feed(url: &str, json_pointer: &str) -> FeedConfig {
+	FeedConfig {
+		url: bounded(url.as_bytes()),
+		method: HttpMethod::Get,
+		headers: headers(&[(b"User-Agent", b"jimmychu0807")]),
+		json_pointer: bounded(json_pointer.as_bytes()),
+		decimals: 0,
+	}
+}
+
+fn set_feed(id: &[u8], config: FeedConfig) {
+	assert_ok!(OcwDemo::set_feed(Origin::root(), id.to_vec(), config));
+}
+
+// Track `symbol` through a coincap feed registered under the id `coincap_id`.
+fn register(symbol: &[u8], coincap_id: &[u8]) {
+	let url = format!("https://api.coincap.io/v2/assets/{}", String::from_utf8_lossy(coincap_id));
+	set_feed(coincap_id, feed(&url, "/data/priceUsd"));
+	assert_ok!(OcwDemo::register_asset(
+		Origin::root(),
+		symbol.to_vec(),
+		vec![coincap_id.to_vec()]
+	));
+}
+
+fn pop_extrinsic(handles: &OffchainHandles) -> Extrinsic {
+	let tx = handles.pool.write().transactions.pop().expect("a transaction was submitted");
+	assert!(handles.pool.read().transactions.is_empty());
+	Extrinsic::decode(&mut &*tx).unwrap()
+}
+
+#[test]
+fn submit_number_signed_works() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let who = sp_core::sr25519::Public::from_raw([1u8; 32]);
+
+		assert_ok!(OcwDemo::submit_number_signed(Origin::signed(who), 42));
+
+		assert_eq!(OcwDemo::numbers(), vec![42]);
+		assert_eq!(
+			System::events().pop().unwrap().event,
+			Event::OcwDemo(crate::Event::NewNumber(Some(who), 42))
+		);
+	});
+}
+
+#[test]
+fn numbers_keep_only_the_latest_ten() {
+	new_test_ext().execute_with(|| {
+		for n in 0..15 {
+			System::set_block_number(n * UnsignedInterval::get());
+			assert_ok!(OcwDemo::submit_number_unsigned(Origin::none(), n));
+		}
+		assert_eq!(OcwDemo::numbers(), (5..15).collect::<Vec<u64>>());
+	});
+}
+
+#[test]
+fn prices_keep_only_the_latest_ten() {
+	let (mut t, handles) = new_offchain_test_ext();
+	t.execute_with(|| {
+		register(b"DOT", b"polkadot");
+		for n in 0..12u64 {
+			// An oracle reports once per round.
+			System::set_block_number(n + 1);
+			let payload = PayloadPrice {
+				symbol: b"DOT".to_vec(),
+				block_number: n + 1,
+				price_tuple: (n, Permill::zero()),
+				sources: 1,
+				public: handles.public,
+			};
+			assert_ok!(OcwDemo::submit_price_unsigned_with_signed_payload(
+				Origin::none(),
+				payload,
+				sp_core::sr25519::Signature::from_raw([0u8; 64]),
+			));
+			close_round(n + 1);
+		}
+		let prices = OcwDemo::prices(symbol(b"DOT"));
+		assert_eq!(prices.len(), 10);
+		assert_eq!(prices.first().map(|p| p.price), Some((2, Permill::zero())));
+		assert_eq!(prices.last().map(|p| p.price), Some((11, Permill::zero())));
+	});
+}
+
+#[test]
+fn genesis_seeds_assets_prices_and_tunables() {
+	let dot_feed = feed(COINCAP_DOT, "/data/priceUsd");
+	let history: Vec<TimestampedPrice> = (0..12)
+		.map(|n| TimestampedPrice { price: (n, Permill::zero()), timestamp: n * 1_000 })
+		.collect();
+	let guard = PriceGuard { max_age: 60_000, max_jump: Permill::from_percent(20) };
+	let schedule = TaskSchedule { interval: 3, offset: 1, enabled: true };
+
+	let mut storage = frame_system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	crate::GenesisConfig::<Test> {
+		feeds: vec![(b"polkadot".to_vec(), dot_feed.clone())],
+		assets: vec![(b"DOT".to_vec(), vec![b"polkadot".to_vec()])],
+		prices: vec![(b"DOT".to_vec(), history.clone())],
+		price_guards: vec![(b"DOT".to_vec(), guard)],
+		submission_mode: SubmissionMode::CommitReveal,
+		task_schedules: vec![(OffchainTask::Prices, schedule)],
+		..Default::default()
+	}
+	.assimilate_storage(&mut storage)
+	.unwrap();
+
+	sp_io::TestExternalities::from(storage).execute_with(|| {
+		assert_eq!(OcwDemo::feeds(feed_id(b"polkadot")), Some(dot_feed));
+		assert_eq!(
+			OcwDemo::tracked_assets(symbol(b"DOT")),
+			Some(AssetInfo { feeds: vec![feed_id(b"polkadot")].try_into().unwrap() })
+		);
+		// Only the latest `MaxHistoryLength` prices are kept.
+		assert_eq!(OcwDemo::prices(symbol(b"DOT")), history[2..].to_vec());
+		assert!(OcwDemo::averages(symbol(b"DOT")).is_some());
+		assert_eq!(OcwDemo::price_guards(symbol(b"DOT")), Some(guard));
+		assert_eq!(OcwDemo::submission_mode(), SubmissionMode::CommitReveal);
+		assert_eq!(OcwDemo::task_schedule(OffchainTask::Prices), schedule);
+	});
+}
+
+#[test]
+fn migration_to_v1_keeps_the_latest_history() {
+	new_test_ext().execute_with(|| {
+		// Version 0 stored unbounded `VecDeque`s, and the DOT prices in a single value.
+		StorageVersion::new(0).put::<OcwDemo>();
+		let numbers: VecDeque<u64> = (0..15).collect();
+		unhashed::put(&Numbers::<Test>::hashed_key(), &numbers);
+		let prices: VecDeque<(u64, Permill)> = (0..12).map(|n| (n, Permill::zero())).collect();
+		unhashed::put(&Prices::<Test>::final_prefix(), &prices);
+		Timestamp::set_timestamp(1_633_660_000_000);
+
+		crate::migrations::v1::migrate::<Test>();
+
+		assert_eq!(OcwDemo::on_chain_storage_version(), StorageVersion::new(1));
+		assert_eq!(OcwDemo::numbers(), (5..15).collect::<Vec<u64>>());
+		assert_eq!(unhashed::get_raw(&Prices::<Test>::final_prefix()), None);
+		assert_eq!(
+			OcwDemo::prices(symbol(b"DOT")),
+			(2..12)
+				.map(|n| TimestampedPrice {
+					price: (n, Permill::zero()),
+					timestamp: 1_633_660_000_000
+				})
+				.collect::<Vec<_>>()
+		);
+
+		// All stamped alike, the prices weigh the same in the time-weighted average.
+		let averages = OcwDemo::averages(symbol(b"DOT")).unwrap();
+		assert_eq!(averages.twap, (6, Permill::from_percent(50)));
+		assert_eq!(averages.ema.0, 10);
+
+		// The offchain worker keeps fetching the DOT price version 0 fetched.
+		let info = OcwDemo::tracked_assets(symbol(b"DOT")).unwrap();
+		assert_eq!(info.feeds, vec![feed_id(b"polkadot")]);
+		let feed = OcwDemo::feeds(feed_id(b"polkadot")).unwrap();
+		assert_eq!(feed.url, COINCAP_DOT.as_bytes().to_vec());
+
+		// Running it again changes nothing.
+		crate::migrations::v1::migrate::<Test>();
+		assert_eq!(OcwDemo::price_history(b"DOT").len(), MaxHistoryLength::get() as usize);
+	});
+}
+
+#[test]
+fn price_queries_read_the_history() {
+	let (mut t, handles) = new_offchain_test_ext();
+	t.execute_with(|| {
+		register(b"DOT", b"polkadot");
+		assert_eq!(OcwDemo::latest_price(b"DOT"), None);
+		assert_eq!(OcwDemo::twap(b"DOT", 3), None);
+
+		for (n, (integer, parts)) in [(10, 0), (20, 0), (31, 500_000)].iter().enumerate() {
+			let round = n as u64 + 1;
+			System::set_block_number(round);
+			assert_ok!(OcwDemo::submit_price_unsigned_with_signed_payload(
+				Origin::none(),
+				PayloadPrice {
+					symbol: b"DOT".to_vec(),
+					block_number: round,
+					price_tuple: (*integer, Permill::from_parts(*parts)),
+					sources: 1,
+					public: handles.public,
+				},
+				sp_core::sr25519::Signature::from_raw([0u8; 64]),
+			));
+			close_round(round);
+		}
+
+		assert_eq!(OcwDemo::latest_price(b"DOT"), Some((31, Permill::from_parts(500_000))));
+		assert_eq!(OcwDemo::price_history(b"DOT").len(), 3);
+		assert_eq!(OcwDemo::twap(b"DOT", 2), Some((25, Permill::from_parts(750_000))));
+		assert_eq!(OcwDemo::twap(b"DOT", 100), Some((20, Permill::from_parts(500_000))));
+		assert_eq!(OcwDemo::twap(b"DOT", 0), None);
+		assert!(OcwDemo::price_history(b"KSM").is_empty());
+		assert!(OcwDemo::price_history(b"TOOLONGSYMBOL").is_empty());
+	});
+}
+
+// Report `price` of `DOT` as `public` in the round `round`, at `timestamp`.
+fn report_price(public: Public, round: u64, timestamp: u64, price: (u64, Permill)) {
+	System::set_block_number(round);
+	Timestamp::set_timestamp(timestamp);
+	assert_ok!(OcwDemo::submit_price_unsigned_with_signed_payload(
+		Origin::none(),
+		PayloadPrice {
+			symbol: b"DOT".to_vec(),
+			block_number: round,
+			price_tuple: price,
+			sources: 1,
+			public,
+		},
+		sp_core::sr25519::Signature::from_raw([0u8; 64]),
+	));
+}
+
+// Close the round `round`, recording the median of its reports. Rounds last
+// `CommitPeriod + RevealPeriod` blocks in the mock.
+fn close_round(round: u64) {
+	let closed_at = round + CommitPeriod::get() + RevealPeriod::get();
+	System::set_block_number(closed_at);
+	OcwDemo::on_initialize(closed_at);
+}
+
+// Record `price` of `DOT` reported by `public` alone in the round `round`, at `timestamp`.
+fn record_price(public: Public, round: u64, timestamp: u64, price: (u64, Permill)) {
+	report_price(public, round, timestamp, price);
+	close_round(round);
+}
+
+#[test]
+fn accepted_prices_earn_rewards() {
+	let (mut t, handles) = new_offchain_test_ext();
+	t.execute_with(|| {
+		register(b"DOT", b"polkadot");
+		let oracle = handles.public;
+
+		report_price(oracle, 1, 1_000, (10, Permill::zero()));
+		assert_eq!(OcwDemo::pending_rewards(oracle), 10);
+		assert_eq!(
+			System::events().pop().unwrap().event,
+			Event::OcwDemo(crate::Event::RewardEarned(oracle, 10))
+		);
+
+		report_price(oracle, 2, 2_000, (10, Permill::zero()));
+		assert_eq!(OcwDemo::pending_rewards(oracle), 20);
+		assert_eq!(OcwDemo::claimed_rewards(oracle), 0);
+	});
+}
+
+#[test]
+fn claim_rewards_pays_out_of_the_pot() {
+	let (mut t, handles) = new_offchain_test_ext();
+	t.execute_with(|| {
+		register(b"DOT", b"polkadot");
+		let oracle = handles.public;
+		assert_noop!(OcwDemo::claim_rewards(Origin::signed(oracle)), Error::<Test>::NoRewards);
+
+		report_price(oracle, 1, 1_000, (10, Permill::zero()));
+		// The pot cannot pay yet.
+		assert!(OcwDemo::claim_rewards(Origin::signed(oracle)).is_err());
+		assert_eq!(OcwDemo::pending_rewards(oracle), 10);
+
+		Balances::make_free_balance_be(&OcwDemo::pot_account(), 100);
+		assert_ok!(OcwDemo::claim_rewards(Origin::signed(oracle)));
+		assert_eq!(Balances::free_balance(oracle), ORACLE_BALANCE - MinReporterBond::get() + 10);
+		assert_eq!(Balances::free_balance(OcwDemo::pot_account()), 90);
+		assert_eq!(OcwDemo::pending_rewards(oracle), 0);
+		assert_eq!(OcwDemo::claimed_rewards(oracle), 10);
+		assert_eq!(
+			System::events().pop().unwrap().event,
+			Event::OcwDemo(crate::Event::RewardClaimed(oracle, 10))
+		);
+
+		assert_noop!(OcwDemo::claim_rewards(Origin::signed(oracle)), Error::<Test>::NoRewards);
+	});
+}
+
+// Add an oracle holding the `demo` key derived from `seed`, bonded with `MinReporterBond`.
+fn bonded_oracle(handles: &OffchainHandles, seed: &str) -> Public {
+	let oracle = SyncCryptoStore::sr25519_generate_new(
+		&*handles.keystore,
+		crate::KEY_TYPE,
+		Some(&format!("{}/{}", PHRASE, seed)),
+	)
+	.unwrap();
+	assert_ok!(OcwDemo::add_oracle(Origin::root(), oracle));
+	Balances::make_free_balance_be(&oracle, ORACLE_BALANCE);
+	assert_ok!(OcwDemo::bond(Origin::signed(oracle), MinReporterBond::get()));
+	oracle
+}
+
+// Run `on_initialize` for the blocks `from..=to`, closing the rounds that expire in them.
+fn close_rounds(from: u64, to: u64) {
+	for block_number in from..=to {
+		System::set_block_number(block_number);
+		OcwDemo::on_initialize(block_number);
+	}
+}
+
+#[test]
+fn oracles_bond_and_unbond() {
+	let (mut t, handles) = new_offchain_test_ext();
+	t.execute_with(|| {
+		let oracle = handles.public;
+		let bond = MinReporterBond::get();
+		assert_eq!(OcwDemo::bonds(oracle), bond);
+		assert_eq!(Balances::reserved_balance(oracle), bond);
+
+		assert_ok!(OcwDemo::bond(Origin::signed(oracle), 50));
+		assert_eq!(OcwDemo::bonds(oracle), bond + 50);
+		assert_eq!(Balances::reserved_balance(oracle), bond + 50);
+		assert_eq!(
+			System::events().pop().unwrap().event,
+			Event::OcwDemo(crate::Event::Bonded(oracle, 50))
+		);
+
+		// Oracles keep `MinReporterBond` bonded.
+		assert_noop!(OcwDemo::unbond(Origin::signed(oracle), 51), Error::<Test>::InsufficientBond);
+		assert_ok!(OcwDemo::unbond(Origin::signed(oracle), 50));
+		assert_eq!(Balances::reserved_balance(oracle), bond);
+		assert_eq!(
+			System::events().pop().unwrap().event,
+			Event::OcwDemo(crate::Event::Unbonded(oracle, 50))
+		);
+
+		assert_ok!(OcwDemo::remove_oracle(Origin::root(), oracle));
+		assert_noop!(OcwDemo::bond(Origin::signed(oracle), 50), Error::<Test>::NotAnOracle);
+		assert_noop!(
+			OcwDemo::unbond(Origin::signed(oracle), bond + 1),
+			Error::<Test>::InsufficientBond
+		);
+		assert_ok!(OcwDemo::unbond(Origin::signed(oracle), bond));
+		assert_eq!(OcwDemo::bonds(oracle), 0);
+		assert_eq!(Balances::free_balance(oracle), ORACLE_BALANCE);
+	});
+}
+
+#[test]
+fn reports_need_a_bond() {
+	let (mut t, handles) = new_offchain_test_ext();
+	t.execute_with(|| {
+		register(b"DOT", b"polkadot");
+		let oracle = handles.public;
+		assert_ok!(OcwDemo::remove_oracle(Origin::root(), oracle));
+		assert_ok!(OcwDemo::unbond(Origin::signed(oracle), MinReporterBond::get()));
+		assert_ok!(OcwDemo::add_oracle(Origin::root(), oracle));
+
+		let (payload, signature) = signed_price(oracle, 1);
+		let call = OcwCall::submit_price_unsigned_with_signed_payload(
+			payload.clone(),
+			signature.clone(),
+		);
+		assert_eq!(
+			OcwDemo::validate_unsigned(TransactionSource::External, &call),
+			InvalidTransaction::Custom(ValidityError::InsufficientBond as u8).into()
+		);
+		assert_noop!(
+			OcwDemo::submit_price_unsigned_with_signed_payload(Origin::none(), payload, signature),
+			Error::<Test>::InsufficientBond
+		);
+	});
+}
+
+#[test]
+fn reports_far_from_the_median_are_slashed() {
+	let (mut t, handles) = new_offchain_test_ext();
+	t.execute_with(|| {
+		register(b"DOT", b"polkadot");
+		let honest = handles.public;
+		let other = bonded_oracle(&handles, "hunter2");
+		let outlier = bonded_oracle(&handles, "hunter3");
+		Balances::make_free_balance_be(&OcwDemo::pot_account(), 100);
+
+		report_price(honest, 1, 1_000, (10, Permill::zero()));
+		report_price(other, 1, 1_000, (10, Permill::from_percent(40)));
+		report_price(outlier, 1, 1_000, (12, Permill::zero()));
+
+		// The round is open until `PRICE_ROUND_LONGEVITY` blocks have passed.
+		close_rounds(2, 4);
+		assert_eq!(OcwDemo::bonds(outlier), MinReporterBond::get());
+
+		close_rounds(5, 5);
+		let slash = DeviationSlash::get() * MinReporterBond::get();
+		assert_eq!(OcwDemo::bonds(outlier), MinReporterBond::get() - slash);
+		assert_eq!(Balances::reserved_balance(outlier), MinReporterBond::get() - slash);
+		assert_eq!(Balances::free_balance(OcwDemo::pot_account()), 100 + slash);
+		assert_eq!(OcwDemo::bonds(honest), MinReporterBond::get());
+		assert_eq!(OcwDemo::bonds(other), MinReporterBond::get());
+		assert_eq!(
+			System::events().pop().unwrap().event,
+			Event::OcwDemo(crate::Event::ReporterSlashed(outlier, b"DOT".to_vec(), 1, slash))
+		);
+
+		// The outlier now bonds less than `MinReporterBond`.
+		let (payload, signature) = signed_price(outlier, 5);
+		assert_noop!(
+			OcwDemo::submit_price_unsigned_with_signed_payload(Origin::none(), payload, signature),
+			Error::<Test>::InsufficientBond
+		);
+	});
+}
+
+#[test]
+fn oracles_missing_rounds_are_suspended() {
+	let (mut t, handles) = new_offchain_test_ext();
+	t.execute_with(|| {
+		register(b"DOT", b"polkadot");
+		let reporter = handles.public;
+		let idle = bonded_oracle(&handles, "hunter2");
+
+		for round in 1..=3 {
+			report_price(reporter, round, round * 1_000, (10, Permill::zero()));
+		}
+		close_rounds(4, 6);
+		assert_eq!(OcwDemo::missed_rounds(idle), 2);
+		assert_eq!(OcwDemo::suspended_until(idle), None);
+
+		close_rounds(7, 7);
+		let until = 7 + SuspensionPeriod::get();
+		assert_eq!(OcwDemo::suspended_until(idle), Some(until));
+		assert_eq!(OcwDemo::missed_rounds(idle), 0);
+		assert_eq!(OcwDemo::missed_rounds(reporter), 0);
+		assert_eq!(
+			System::events().pop().unwrap().event,
+			Event::OcwDemo(crate::Event::ReporterSuspended(idle, until))
+		);
+
+		let (payload, signature) = signed_price(idle, 7);
+		let call = OcwCall::submit_price_unsigned_with_signed_payload(
+			payload.clone(),
+			signature.clone(),
+		);
+		assert_eq!(
+			OcwDemo::validate_unsigned(TransactionSource::External, &call),
+			InvalidTransaction::Custom(ValidityError::Suspended as u8).into()
+		);
+		assert_noop!(
+			OcwDemo::submit_price_unsigned_with_signed_payload(Origin::none(), payload, signature),
+			Error::<Test>::OracleSuspended
+		);
+
+		System::set_block_number(until);
+		let (payload, signature) = signed_price(idle, until);
+		assert_ok!(OcwDemo::submit_price_unsigned_with_signed_payload(
+			Origin::none(),
+			payload,
+			signature,
+		));
+	});
+}
+
+fn commit(public: Public, round: u64, price: (u64, Permill), salt: [u8; 32]) -> DispatchResult {
+	OcwDemo::commit_price_unsigned_with_signed_payload(
+		Origin::none(),
+		PayloadCommit {
+			symbol: b"DOT".to_vec(),
+			block_number: round,
+			commitment: OcwDemo::commitment(&public, price, &salt),
+			public,
+		},
+		sp_core::sr25519::Signature::from_raw([0u8; 64]),
+	)
+}
+
+fn reveal(public: Public, round: u64, price: (u64, Permill), salt: [u8; 32]) -> DispatchResult {
+	OcwDemo::reveal_price_unsigned_with_signed_payload(
+		Origin::none(),
+		PayloadReveal {
+			symbol: b"DOT".to_vec(),
+			block_number: round,
+			price_tuple: price,
+			sources: 1,
+			salt,
+			public,
+		},
+		sp_core::sr25519::Signature::from_raw([0u8; 64]),
+	)
+}
+
+#[test]
+fn commit_reveal_phases_follow_the_windows() {
+	assert_eq!(phase(10u64, 9, 2, 3), Phase::Pending);
+	assert_eq!(phase(10u64, 10, 2, 3), Phase::Commit);
+	assert_eq!(phase(10u64, 11, 2, 3), Phase::Commit);
+	assert_eq!(phase(10u64, 12, 2, 3), Phase::Reveal);
+	assert_eq!(phase(10u64, 14, 2, 3), Phase::Reveal);
+	assert_eq!(phase(10u64, 15, 2, 3), Phase::Closed);
+}
+
+#[test]
+fn set_submission_mode_requires_root() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let who = sp_core::sr25519::Public::from_raw([1u8; 32]);
+		assert_eq!(OcwDemo::submission_mode(), SubmissionMode::Direct);
+		assert_noop!(
+			OcwDemo::set_submission_mode(Origin::signed(who), SubmissionMode::CommitReveal),
+			BadOrigin
+		);
+		assert_ok!(OcwDemo::set_submission_mode(Origin::root(), SubmissionMode::CommitReveal));
+		assert_eq!(OcwDemo::submission_mode(), SubmissionMode::CommitReveal);
+		assert_eq!(
+			System::events().pop().unwrap().event,
+			Event::OcwDemo(crate::Event::SubmissionModeSet(SubmissionMode::CommitReveal))
+		);
+	});
+}
+
+#[test]
+fn commit_reveal_records_the_median_of_valid_reveals() {
+	let (mut t, handles) = new_offchain_test_ext();
+	t.execute_with(|| {
+		register(b"DOT", b"polkadot");
+		assert_ok!(OcwDemo::set_submission_mode(Origin::root(), SubmissionMode::CommitReveal));
+		let first = handles.public;
+		let second = bonded_oracle(&handles, "hunter2");
+		let cheater = bonded_oracle(&handles, "hunter3");
+		let low = (10, Permill::zero());
+		let high = (10, Permill::from_percent(40));
+
+		// Direct reports are refused.
+		assert_noop!(
+			OcwDemo::submit_price_unsigned_with_signed_payload(
+				Origin::none(),
+				signed_price(first, 1).0,
+				signed_price(first, 1).1,
+			),
+			Error::<Test>::WrongSubmissionMode
+		);
+
+		assert_ok!(commit(first, 1, low, [1; 32]));
+		assert_ok!(commit(second, 1, high, [2; 32]));
+		assert_ok!(commit(cheater, 1, high, [3; 32]));
+		assert_noop!(commit(first, 1, high, [4; 32]), Error::<Test>::AlreadyCommitted);
+		assert_eq!(
+			System::events().pop().unwrap().event,
+			Event::OcwDemo(crate::Event::PriceCommitted(cheater, b"DOT".to_vec(), 1))
+		);
+
+		// The reveal window opens after `CommitPeriod` blocks.
+		System::set_block_number(2);
+		assert_noop!(reveal(first, 1, low, [1; 32]), Error::<Test>::OutsideWindow);
+		System::set_block_number(3);
+		assert_noop!(commit(first, 1, low, [1; 32]), Error::<Test>::OutsideWindow);
+
+		assert_ok!(reveal(first, 1, low, [1; 32]));
+		assert_ok!(reveal(second, 1, high, [2; 32]));
+		assert_eq!(
+			System::events().pop().unwrap().event,
+			Event::OcwDemo(crate::Event::RewardEarned(second, ReporterReward::get()))
+		);
+		assert_noop!(reveal(first, 1, low, [1; 32]), Error::<Test>::InvalidReveal);
+		// A reveal must match the commitment.
+		assert_noop!(reveal(cheater, 1, low, [3; 32]), Error::<Test>::InvalidReveal);
+		assert_noop!(reveal(cheater, 1, high, [4; 32]), Error::<Test>::InvalidReveal);
+		assert!(OcwDemo::price_history(b"DOT").is_empty());
+
+		close_rounds(4, 5);
+		assert_eq!(OcwDemo::price_history(b"DOT"), vec![(10, Permill::from_percent(20))]);
+		assert_eq!(crate::Commitments::<Test>::iter_prefix(1).count(), 0);
+		assert!(System::events().iter().any(|record| {
+			record.event ==
+				Event::OcwDemo(crate::Event::NewPrice(
+					None,
+					b"DOT".to_vec(),
+					(10, Permill::from_percent(20))
+				))
+		}));
+		// Committing without revealing counts as a missed round.
+		assert_eq!(OcwDemo::missed_rounds(cheater), 1);
+		assert_eq!(OcwDemo::missed_rounds(first), 0);
+	});
+}
+
+#[test]
+fn reveals_are_validated_against_the_commitment() {
+	let (mut t, handles) = new_offchain_test_ext();
+	t.execute_with(|| {
+		register(b"DOT", b"polkadot");
+		assert_ok!(OcwDemo::set_submission_mode(Origin::root(), SubmissionMode::CommitReveal));
+		let price = (28, Permill::zero());
+		assert_ok!(commit(handles.public, 1, price, [7; 32]));
+		System::set_block_number(1 + CommitPeriod::get());
+
+		let validate = |salt| {
+			let payload = PayloadReveal {
+				symbol: b"DOT".to_vec(),
+				block_number: 1,
+				price_tuple: price,
+				sources: 1,
+				salt,
+				public: handles.public,
+			};
+			let signature = <PayloadReveal<Public, u64> as SignedPayload<Test>>::sign::<
+				crate::crypto::TestAuthId,
+			>(&payload)
+			.unwrap();
+			OcwDemo::validate_unsigned(
+				TransactionSource::External,
+				&OcwCall::reveal_price_unsigned_with_signed_payload(payload, signature),
+			)
+		};
+		assert!(validate([7; 32]).is_ok());
+		assert_eq!(
+			validate([8; 32]),
+			InvalidTransaction::Custom(ValidityError::InvalidReveal as u8).into()
+		);
+	});
+}
+
+fn guard(max_age: u64, max_jump: u32) -> PriceGuard {
+	PriceGuard { max_age, max_jump: Permill::from_percent(max_jump) }
+}
+
+#[test]
+fn set_price_guard_requires_root_and_a_tracked_asset() {
+	let (mut t, handles) = new_offchain_test_ext();
+	t.execute_with(|| {
+		let who = handles.public;
+		assert_noop!(
+			OcwDemo::set_price_guard(Origin::root(), b"DOT".to_vec(), Some(guard(1_000, 10))),
+			Error::<Test>::AssetNotTracked
+		);
+		register(b"DOT", b"polkadot");
+		assert_noop!(
+			OcwDemo::set_price_guard(Origin::signed(who), b"DOT".to_vec(), Some(guard(1_000, 10))),
+			BadOrigin
+		);
+		assert_ok!(OcwDemo::set_price_guard(
+			Origin::root(),
+			b"DOT".to_vec(),
+			Some(guard(1_000, 10))
+		));
+		assert_eq!(OcwDemo::price_guards(symbol(b"DOT")), Some(guard(1_000, 10)));
+		assert_eq!(
+			System::events().pop().unwrap().event,
+			Event::OcwDemo(crate::Event::PriceGuardSet(b"DOT".to_vec(), Some(guard(1_000, 10))))
+		);
+		assert_ok!(OcwDemo::set_price_guard(Origin::root(), b"DOT".to_vec(), None));
+		assert_eq!(OcwDemo::price_guards(symbol(b"DOT")), None);
+	});
+}
+
+#[test]
+fn stale_prices_are_reported_once() {
+	let (mut t, handles) = new_offchain_test_ext();
+	t.execute_with(|| {
+		register(b"DOT", b"polkadot");
+		let max_age = Some(guard(5_000, 0));
+		assert_ok!(OcwDemo::set_price_guard(Origin::root(), b"DOT".to_vec(), max_age));
+		record_price(handles.public, 1, 1_000, (10, Permill::zero()));
+		let stale = Event::OcwDemo(crate::Event::PriceStale(b"DOT".to_vec(), 1_000));
+		let stale_events = || System::events().iter().filter(|r| r.event == stale).count();
+
+		Timestamp::set_timestamp(6_000);
+		OcwDemo::on_initialize(2);
+		assert_eq!(stale_events(), 0);
+		assert_eq!(OcwDemo::fresh_price(b"DOT"), Some((10, Permill::zero())));
+
+		Timestamp::set_timestamp(6_001);
+		OcwDemo::on_initialize(3);
+		OcwDemo::on_initialize(4);
+		assert_eq!(stale_events(), 1);
+		assert_eq!(OcwDemo::fresh_price(b"DOT"), None);
+		assert_eq!(
+			OcwDemo::latest_timestamped_price(b"DOT"),
+			Some(((10, Permill::zero()), 1_000))
+		);
+
+		// A new price makes the asset fresh again.
+		record_price(handles.public, 5, 7_000, (11, Permill::zero()));
+		assert_eq!(OcwDemo::fresh_price(b"DOT"), Some((11, Permill::zero())));
+		Timestamp::set_timestamp(12_001);
+		OcwDemo::on_initialize(6);
+		assert_eq!(
+			System::events().pop().unwrap().event,
+			Event::OcwDemo(crate::Event::PriceStale(b"DOT".to_vec(), 7_000))
+		);
+	});
+}
+
+#[test]
+fn price_jumps_trip_the_circuit_breaker() {
+	let (mut t, handles) = new_offchain_test_ext();
+	t.execute_with(|| {
+		register(b"DOT", b"polkadot");
+		assert_ok!(OcwDemo::set_price_guard(Origin::root(), b"DOT".to_vec(), Some(guard(0, 50))));
+		record_price(handles.public, 1, 1_000, (10, Permill::zero()));
+		record_price(handles.public, 2, 2_000, (15, Permill::zero()));
+		assert_eq!(OcwDemo::latest_price(b"DOT"), Some((15, Permill::zero())));
+
+		// A 90% drop is quarantined.
+		record_price(handles.public, 3, 3_000, (1, Permill::from_percent(50)));
+		assert_eq!(OcwDemo::latest_price(b"DOT"), Some((15, Permill::zero())));
+		assert_eq!(OcwDemo::fresh_price(b"DOT"), None);
+		assert!(System::events().iter().any(|r| {
+			r.event ==
+				Event::OcwDemo(crate::Event::CircuitBreakerTripped(
+					b"DOT".to_vec(),
+					(15, Permill::zero()),
+					(1, Permill::from_percent(50)),
+				))
+		}));
+
+		// Later prices are held back too, until root resets the breaker.
+		record_price(handles.public, 4, 4_000, (1, Permill::from_percent(40)));
+		assert_eq!(
+			OcwDemo::quarantined(symbol(b"DOT")),
+			Some(TimestampedPrice { price: (1, Permill::from_percent(40)), timestamp: 4_000 })
+		);
+		assert_eq!(OcwDemo::price_history(b"DOT").len(), 2);
+
+		assert_noop!(
+			OcwDemo::reset_circuit_breaker(Origin::signed(handles.public), b"DOT".to_vec(), true),
+			BadOrigin
+		);
+		assert_ok!(OcwDemo::reset_circuit_breaker(Origin::root(), b"DOT".to_vec(), true));
+		assert_eq!(OcwDemo::latest_price(b"DOT"), Some((1, Permill::from_percent(40))));
+		assert_eq!(
+			System::events().pop().unwrap().event,
+			Event::OcwDemo(crate::Event::CircuitBreakerReset(b"DOT".to_vec(), true))
+		);
+		assert_noop!(
+			OcwDemo::reset_circuit_breaker(Origin::root(), b"DOT".to_vec(), true),
+			Error::<Test>::BreakerNotTripped
+		);
+
+		// Prices within the limit are recorded as usual.
+		record_price(handles.public, 5, 5_000, (2, Permill::zero()));
+		assert_eq!(OcwDemo::latest_price(b"DOT"), Some((2, Permill::zero())));
+	});
+}
+
+#[test]
+fn reset_circuit_breaker_can_drop_the_quarantined_price() {
+	let (mut t, handles) = new_offchain_test_ext();
+	t.execute_with(|| {
+		register(b"DOT", b"polkadot");
+		assert_ok!(OcwDemo::set_price_guard(Origin::root(), b"DOT".to_vec(), Some(guard(0, 10))));
+		record_price(handles.public, 1, 1_000, (10, Permill::zero()));
+		record_price(handles.public, 2, 2_000, (100, Permill::zero()));
+
+		assert_ok!(OcwDemo::reset_circuit_breaker(Origin::root(), b"DOT".to_vec(), false));
+		assert_eq!(OcwDemo::price_history(b"DOT"), vec![(10, Permill::zero())]);
+		assert_eq!(OcwDemo::quarantined(symbol(b"DOT")), None);
+	});
+}
+
+#[test]
+fn averages_are_updated_with_every_price() {
+	let (mut t, handles) = new_offchain_test_ext();
+	t.execute_with(|| {
+		register(b"DOT", b"polkadot");
+		assert_eq!(<OcwDemo as AveragePrice>::time_weighted_average(b"DOT"), None);
+
+		record_price(handles.public, 1, 1_000, (10, Permill::zero()));
+		assert_eq!(
+			OcwDemo::averages(symbol(b"DOT")),
+			Some(PriceAverages { twap: (10, Permill::zero()), ema: (10, Permill::zero()) })
+		);
+
+		record_price(handles.public, 2, 3_000, (20, Permill::zero()));
+		record_price(handles.public, 3, 4_000, (40, Permill::zero()));
+		assert_eq!(
+			OcwDemo::prices(symbol(b"DOT")).last(),
+			Some(&TimestampedPrice { price: (40, Permill::zero()), timestamp: 4_000 })
+		);
+		// 10 held for 2s and 20 for 1s.
+		assert_eq!(
+			<OcwDemo as AveragePrice>::time_weighted_average(b"DOT"),
+			Some((13, Permill::from_parts(333_333)))
+		);
+		// Each new price weighs 50%: 10, then 15, then 27.5.
+		assert_eq!(
+			<OcwDemo as AveragePrice>::exponential_moving_average(b"DOT"),
+			Some((27, Permill::from_percent(50)))
+		);
+		assert_eq!(OcwDemo::twap(b"DOT", 2), Some((20, Permill::zero())));
+
+		assert_ok!(OcwDemo::deregister_asset(Origin::root(), b"DOT".to_vec()));
+		assert_eq!(<OcwDemo as AveragePrice>::exponential_moving_average(b"DOT"), None);
+	});
+}
+
+#[test]
+fn validate_unsigned_rejects_forged_price_payload() {
+	let (mut t, handles) = new_offchain_test_ext();
+	t.execute_with(|| {
+		register(b"DOT", b"polkadot");
+		let payload = PayloadPrice {
+			symbol: b"DOT".to_vec(),
+			block_number: 1,
+			price_tuple: (7, Permill::zero()),
+			sources: 1,
+			public: handles.public,
+		};
+		let signature = <PayloadPrice<Public, u64> as SignedPayload<Test>>::sign::<
+			crate::crypto::TestAuthId,
+		>(&payload)
+		.unwrap();
+
+		let call =
+			OcwCall::submit_price_unsigned_with_signed_payload(payload.clone(), signature.clone());
+		assert!(OcwDemo::validate_unsigned(TransactionSource::External, &call).is_ok());
+
+		let tampered = PayloadPrice { price_tuple: (700, Permill::zero()), ..payload };
+		let call = OcwCall::submit_price_unsigned_with_signed_payload(tampered, signature);
+		assert_eq!(
+			OcwDemo::validate_unsigned(TransactionSource::External, &call),
+			InvalidTransaction::BadProof.into(),
+		);
+	});
+}
+
+#[test]
+fn register_and_deregister_asset_works() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let oracle = sp_core::sr25519::Public::from_raw([1u8; 32]);
+		assert_ok!(OcwDemo::add_oracle(Origin::root(), oracle));
+		register(b"DOT", b"polkadot");
+		assert_eq!(
+			OcwDemo::tracked_assets(symbol(b"DOT")),
+			Some(AssetInfo { feeds: vec![feed_id(b"polkadot")].try_into().unwrap() })
+		);
+		assert_noop!(
+			OcwDemo::register_asset(Origin::root(), b"DOT".to_vec(), vec![b"polkadot".to_vec()]),
+			Error::<Test>::AssetAlreadyTracked
+		);
+
+		assert_ok!(OcwDemo::submit_price_unsigned_with_signed_payload(
+			Origin::none(),
+			PayloadPrice {
+				symbol: b"DOT".to_vec(),
+				block_number: 1,
+				price_tuple: (28, Permill::zero()),
+				sources: 1,
+				public: oracle,
+			},
+			sp_core::sr25519::Signature::from_raw([0u8; 64]),
+		));
+		close_round(1);
+		assert_eq!(OcwDemo::prices(symbol(b"DOT")).len(), 1);
+
+		assert_ok!(OcwDemo::deregister_asset(Origin::root(), b"DOT".to_vec()));
+		assert_eq!(OcwDemo::tracked_assets(symbol(b"DOT")), None);
+		assert!(OcwDemo::prices(symbol(b"DOT")).is_empty());
+		assert_eq!(
+			System::events().pop().unwrap().event,
+			Event::OcwDemo(crate::Event::AssetDeregistered(b"DOT".to_vec()))
+		);
+	});
+}
+
+#[test]
+fn reregistered_assets_start_from_a_clean_round() {
+	let (mut t, handles) = new_offchain_test_ext();
+	t.execute_with(|| {
+		register(b"DOT", b"polkadot");
+		let oracle = handles.public;
+		report_price(oracle, 1, 1_000, (28, Permill::zero()));
+		assert!(crate::LastReportRound::<Test>::contains_key(symbol(b"DOT"), oracle));
+		assert!(crate::RoundReports::<Test>::contains_key(1, symbol(b"DOT")));
+
+		assert_ok!(OcwDemo::deregister_asset(Origin::root(), b"DOT".to_vec()));
+		assert!(!crate::LastReportRound::<Test>::contains_key(symbol(b"DOT"), oracle));
+		assert!(!crate::RoundReports::<Test>::contains_key(1, symbol(b"DOT")));
+
+		// The oracle may report the round again, and only the new report counts.
+		register(b"DOT", b"polkadot");
+		report_price(oracle, 1, 1_000, (30, Permill::zero()));
+		assert_eq!(
+			crate::RoundReports::<Test>::get(1, symbol(b"DOT")).into_inner(),
+			vec![(oracle, (30, Permill::zero()))]
+		);
+	});
+}
+
+#[test]
+fn asset_registry_is_root_only_and_bounded() {
+	new_test_ext().execute_with(|| {
+		let who = sp_core::sr25519::Public::from_raw([1u8; 32]);
+		assert_noop!(
+			OcwDemo::register_asset(Origin::signed(who), b"DOT".to_vec(), vec![b"dot".to_vec()]),
+			BadOrigin
+		);
+		assert_noop!(
+			OcwDemo::register_asset(Origin::root(), b"TOOLONGSYM".to_vec(), vec![b"x".to_vec()]),
+			Error::<Test>::SymbolTooLong
+		);
+		assert_noop!(
+			OcwDemo::register_asset(Origin::root(), b"DOT".to_vec(), vec![]),
+			Error::<Test>::NoFeeds
+		);
+		let feeds = vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec(), b"d".to_vec()];
+		assert_noop!(
+			OcwDemo::register_asset(Origin::root(), b"DOT".to_vec(), feeds),
+			Error::<Test>::TooManyFeeds
+		);
+		assert_noop!(
+			OcwDemo::register_asset(Origin::root(), b"DOT".to_vec(), vec![b"dot".to_vec()]),
+			Error::<Test>::FeedNotFound
+		);
+		assert_noop!(
+			OcwDemo::deregister_asset(Origin::root(), b"KSM".to_vec()),
+			Error::<Test>::AssetNotTracked
+		);
+	});
+}
+
+#[test]
+fn prices_of_untracked_assets_are_rejected() {
+	let (mut t, handles) = new_offchain_test_ext();
+	t.execute_with(|| {
+		let payload = PayloadPrice {
+			symbol: b"KSM".to_vec(),
+			block_number: 1,
+			price_tuple: (300, Permill::zero()),
+			sources: 1,
+			public: handles.public,
+		};
+		let signature = <PayloadPrice<Public, u64> as SignedPayload<Test>>::sign::<
+			crate::crypto::TestAuthId,
+		>(&payload)
+		.unwrap();
+		let call =
+			OcwCall::submit_price_unsigned_with_signed_payload(payload.clone(), signature.clone());
+		assert_eq!(
+			OcwDemo::validate_unsigned(TransactionSource::External, &call),
+			InvalidTransaction::Call.into(),
+		);
+		assert_noop!(
+			OcwDemo::submit_price_unsigned_with_signed_payload(Origin::none(), payload, signature),
+			Error::<Test>::AssetNotTracked
+		);
+	});
+}
+
+#[test]
+fn feeds_are_managed_by_root() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let who = sp_core::sr25519::Public::from_raw([1u8; 32]);
+		let config = feed(COINCAP_DOT, "/data/priceUsd");
+
+		assert_noop!(
+			OcwDemo::set_feed(Origin::signed(who), b"dot".to_vec(), config.clone()),
+			BadOrigin
+		);
+		assert_noop!(
+			OcwDemo::set_feed(Origin::root(), b"a-very-long-feed-id".to_vec(), config.clone()),
+			Error::<Test>::FeedIdTooLong
+		);
+		for invalid in vec![
+			FeedConfig { url: bounded(b"ftp://example.com"), ..config.clone() },
+			FeedConfig { url: bounded(b"http://\xff"), ..config.clone() },
+			FeedConfig { json_pointer: bounded(b"data/priceUsd"), ..config.clone() },
+			FeedConfig { headers: headers(&[(b"", b"x")]), ..config.clone() },
+		] {
+			assert_noop!(
+				OcwDemo::set_feed(Origin::root(), b"dot".to_vec(), invalid),
+				Error::<Test>::InvalidFeedConfig
+			);
+		}
+
+		assert_ok!(OcwDemo::set_feed(Origin::root(), b"dot".to_vec(), config.clone()));
+		assert_eq!(OcwDemo::feeds(feed_id(b"dot")), Some(config));
+		assert_ok!(OcwDemo::remove_feed(Origin::root(), b"dot".to_vec()));
+		assert_eq!(OcwDemo::feeds(feed_id(b"dot")), None);
+		assert_eq!(
+			System::events().pop().unwrap().event,
+			Event::OcwDemo(crate::Event::FeedRemoved(b"dot".to_vec()))
+		);
+		assert_noop!(
+			OcwDemo::remove_feed(Origin::root(), b"dot".to_vec()),
+			Error::<Test>::FeedNotFound
+		);
+	});
+}
+
+#[test]
+fn oracles_are_seeded_at_genesis_and_managed_by_root() {
+	let (mut t, handles) = new_offchain_test_ext();
+	t.execute_with(|| {
+		let who = sp_core::sr25519::Public::from_raw([1u8; 32]);
+		assert!(Oracles::<Test>::contains_key(handles.public));
+		assert!(!OcwDemo::is_oracle(&who));
+
+		assert_noop!(OcwDemo::add_oracle(Origin::signed(who), who), BadOrigin);
+		assert_ok!(OcwDemo::add_oracle(Origin::root(), who));
+		assert!(OcwDemo::is_oracle(&who));
+		assert_eq!(
+			System::events().pop().unwrap().event,
+			Event::OcwDemo(crate::Event::OracleAdded(who))
+		);
+		assert_noop!(OcwDemo::add_oracle(Origin::root(), who), Error::<Test>::AlreadyOracle);
+
+		assert_noop!(OcwDemo::remove_oracle(Origin::signed(who), who), BadOrigin);
+		assert_ok!(OcwDemo::remove_oracle(Origin::root(), who));
+		assert!(!OcwDemo::is_oracle(&who));
+		assert_noop!(OcwDemo::remove_oracle(Origin::root(), who), Error::<Test>::NotAnOracle);
+	});
+}
+
+#[test]
+fn oracles_are_capped_at_max_oracles() {
+	let (mut t, _) = new_offchain_test_ext();
+	t.execute_with(|| {
+		// The genesis oracle takes the first slot.
+		for n in 1..MaxOracles::get() as u8 {
+			let who = sp_core::sr25519::Public::from_raw([n; 32]);
+			assert_ok!(OcwDemo::add_oracle(Origin::root(), who));
+		}
+		assert_eq!(OracleCount::<Test>::get(), MaxOracles::get());
+
+		let who = sp_core::sr25519::Public::from_raw([0xff; 32]);
+		assert_noop!(OcwDemo::add_oracle(Origin::root(), who), Error::<Test>::TooManyOracles);
+
+		let removed = sp_core::sr25519::Public::from_raw([1; 32]);
+		assert_ok!(OcwDemo::remove_oracle(Origin::root(), removed));
+		assert_eq!(OracleCount::<Test>::get(), MaxOracles::get() - 1);
+		assert_ok!(OcwDemo::add_oracle(Origin::root(), who));
+	});
+}
+
+#[test]
+fn payloads_of_unknown_oracles_are_rejected() {
+	let (mut t, handles) = new_offchain_test_ext();
+	t.execute_with(|| {
+		register(b"DOT", b"polkadot");
+		assert_ok!(OcwDemo::remove_oracle(Origin::root(), handles.public));
+
+		let payload = PayloadPrice {
+			symbol: b"DOT".to_vec(),
+			block_number: 1,
+			price_tuple: (28, Permill::zero()),
+			sources: 1,
+			public: handles.public,
+		};
+		let signature = <PayloadPrice<Public, u64> as SignedPayload<Test>>::sign::<
+			crate::crypto::TestAuthId,
+		>(&payload)
+		.unwrap();
+		let call =
+			OcwCall::submit_price_unsigned_with_signed_payload(payload.clone(), signature.clone());
+		assert_eq!(
+			OcwDemo::validate_unsigned(TransactionSource::External, &call),
+			InvalidTransaction::Custom(ValidityError::NotAnOracle as u8).into(),
+		);
+		assert_noop!(
+			OcwDemo::submit_price_unsigned_with_signed_payload(Origin::none(), payload, signature),
+			Error::<Test>::NotAnOracle
+		);
+
+		let payload = Payload { number: 7, public: handles.public };
+		let signature =
+			<Payload<Public> as SignedPayload<Test>>::sign::<crate::crypto::TestAuthId>(&payload)
+				.unwrap();
+		let call = OcwCall::submit_number_unsigned_with_signed_payload(
+			payload.clone(),
+			signature.clone(),
+		);
+		assert_eq!(
+			OcwDemo::validate_unsigned(TransactionSource::External, &call),
+			InvalidTransaction::Custom(ValidityError::NotAnOracle as u8).into(),
+		);
+		assert_noop!(
+			OcwDemo::submit_number_unsigned_with_signed_payload(Origin::none(), payload, signature),
+			Error::<Test>::NotAnOracle
+		);
+	});
+}
+
+fn signed_price(
+	public: Public,
+	block_number: u64,
+) -> (PayloadPrice<Public, u64>, <Test as SigningTypes>::Signature) {
+	let payload = PayloadPrice {
+		symbol: b"DOT".to_vec(),
+		block_number,
+		price_tuple: (28, Permill::zero()),
+		sources: 1,
+		public,
+	};
+	let signature = <PayloadPrice<Public, u64> as SignedPayload<Test>>::sign::<
+		crate::crypto::TestAuthId,
+	>(&payload)
+	.unwrap();
+	(payload, signature)
+}
+
+#[test]
+fn price_payloads_are_bound_to_a_recent_round() {
+	let (mut t, handles) = new_offchain_test_ext();
+	t.execute_with(|| {
+		register(b"DOT", b"polkadot");
+		System::set_block_number(10);
+		let validate = |block_number| {
+			let (payload, signature) = signed_price(handles.public, block_number);
+			OcwDemo::validate_unsigned(
+				TransactionSource::External,
+				&OcwCall::submit_price_unsigned_with_signed_payload(payload, signature),
+			)
+		};
+
+		let future = InvalidTransaction::Custom(ValidityError::FutureRound as u8);
+		let stale = InvalidTransaction::Custom(ValidityError::StaleRound as u8);
+		assert_eq!(validate(11), future.into());
+		assert_eq!(validate(6), stale.into());
+		assert!(validate(7).is_ok());
+		assert!(validate(10).is_ok());
+
+		let (payload, signature) = signed_price(handles.public, 11);
+		assert_noop!(
+			OcwDemo::submit_price_unsigned_with_signed_payload(Origin::none(), payload, signature),
+			Error::<Test>::FutureRound
+		);
+		let (payload, signature) = signed_price(handles.public, 6);
+		assert_noop!(
+			OcwDemo::submit_price_unsigned_with_signed_payload(Origin::none(), payload, signature),
+			Error::<Test>::StaleRound
+		);
+	});
+}
+
+#[test]
+fn price_payloads_cannot_be_replayed() {
+	let (mut t, handles) = new_offchain_test_ext();
+	t.execute_with(|| {
+		register(b"DOT", b"polkadot");
+		System::set_block_number(9);
+		let (payload, signature) = signed_price(handles.public, 8);
+		assert_ok!(OcwDemo::submit_price_unsigned_with_signed_payload(
+			Origin::none(),
+			payload.clone(),
+			signature.clone(),
+		));
+
+		System::set_block_number(10);
+		let call =
+			OcwCall::submit_price_unsigned_with_signed_payload(payload.clone(), signature.clone());
+		assert_eq!(
+			OcwDemo::validate_unsigned(TransactionSource::External, &call),
+			InvalidTransaction::Custom(ValidityError::StaleRound as u8).into(),
+		);
+		assert_noop!(
+			OcwDemo::submit_price_unsigned_with_signed_payload(Origin::none(), payload, signature),
+			Error::<Test>::StaleRound
+		);
+
+		// Earlier rounds are stale as well, later ones are accepted.
+		let (payload, signature) = signed_price(handles.public, 7);
+		assert_noop!(
+			OcwDemo::submit_price_unsigned_with_signed_payload(Origin::none(), payload, signature),
+			Error::<Test>::StaleRound
+		);
+		let (payload, signature) = signed_price(handles.public, 10);
+		assert_ok!(OcwDemo::submit_price_unsigned_with_signed_payload(
+			Origin::none(),
+			payload,
+			signature,
+		));
+		assert_eq!(crate::RoundReports::<Test>::get(8, symbol(b"DOT")).len(), 1);
+		assert_eq!(crate::RoundReports::<Test>::get(10, symbol(b"DOT")).len(), 1);
+	});
+}
+
+#[test]
+fn oracles_reporting_the_same_round_provide_distinct_tags() {
+	let (mut t, handles) = new_offchain_test_ext();
+	t.execute_with(|| {
+		register(b"DOT", b"polkadot");
+		let other = SyncCryptoStore::sr25519_generate_new(
+			&*handles.keystore,
+			crate::KEY_TYPE,
+			Some(&format!("{}/hunter2", PHRASE)),
+		)
+		.unwrap();
+		assert_ok!(OcwDemo::add_oracle(Origin::root(), other));
+		Balances::make_free_balance_be(&other, ORACLE_BALANCE);
+		assert_ok!(OcwDemo::bond(Origin::signed(other), MinReporterBond::get()));
+
+		let provides = |public| {
+			let (payload, signature) = signed_price(public, 1);
+			OcwDemo::validate_unsigned(
+				TransactionSource::External,
+				&OcwCall::submit_price_unsigned_with_signed_payload(payload, signature),
+			)
+			.unwrap()
+			.provides
+		};
+		assert_ne!(provides(handles.public), provides(other));
+		assert_eq!(provides(handles.public), provides(handles.public));
+	});
+}
+
+#[test]
+fn unsigned_numbers_are_rate_limited() {
+	let (mut t, handles) = new_offchain_test_ext();
+	t.execute_with(|| {
+		System::set_block_number(5);
+		assert_ok!(OcwDemo::submit_number_unsigned(Origin::none(), 1));
+		assert_eq!(OcwDemo::next_unsigned_at(), 7);
+
+		System::set_block_number(6);
+		let too_early = InvalidTransaction::Custom(ValidityError::TooEarly as u8);
+		let call = OcwCall::submit_number_unsigned(2);
+		assert_eq!(
+			OcwDemo::validate_unsigned(TransactionSource::External, &call),
+			too_early.into()
+		);
+		assert_noop!(OcwDemo::submit_number_unsigned(Origin::none(), 2), Error::<Test>::TooEarly);
+
+		let payload = Payload { number: 2, public: handles.public };
+		let signature =
+			<Payload<Public> as SignedPayload<Test>>::sign::<crate::crypto::TestAuthId>(&payload)
+				.unwrap();
+		let call = OcwCall::submit_number_unsigned_with_signed_payload(
+			payload.clone(),
+			signature.clone(),
+		);
+		assert_eq!(
+			OcwDemo::validate_unsigned(TransactionSource::External, &call),
+			too_early.into()
+		);
+		assert_noop!(
+			OcwDemo::submit_number_unsigned_with_signed_payload(Origin::none(), payload, signature),
+			Error::<Test>::TooEarly
+		);
+
+		System::set_block_number(7);
+		assert!(OcwDemo::validate_unsigned(TransactionSource::External, &call).is_ok());
+		assert_ok!(OcwDemo::submit_number_unsigned(Origin::none(), 3));
+		assert_eq!(OcwDemo::numbers(), vec![1, 3]);
+	});
+}
+
+#[test]
+fn next_unsigned_at_saturates() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(u64::MAX - 1);
+		assert_ok!(OcwDemo::submit_number_unsigned(Origin::none(), 1));
+		assert_eq!(OcwDemo::next_unsigned_at(), u64::MAX);
+	});
+}
+
+#[test]
+fn offchain_worker_waits_for_next_unsigned_at() {
+	let (mut t, handles) = new_offchain_test_ext();
+	t.execute_with(|| {
+		NextUnsignedAt::<Test>::put(9);
+
+		// Sent at block 7, the number would be validated at block 8 at the earliest.
+		OcwDemo::offchain_worker(7);
+		assert!(handles.pool.read().transactions.is_empty());
+
+		OcwDemo::offchain_worker(8);
+		let tx = pop_extrinsic(&handles);
+		assert!(matches!(
+			tx.call,
+			Call::OcwDemo(OcwCall::submit_number_unsigned_with_signed_payload(..))
+		));
+	});
+}
+
+#[test]
+fn task_schedules_are_managed_by_root() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let who = sp_core::sr25519::Public::from_raw([1u8; 32]);
+		let schedule = TaskSchedule { interval: 1, offset: 0, enabled: true };
+		assert_eq!(
+			OcwDemo::task_schedule(OffchainTask::Prices),
+			TaskSchedule { interval: 5, offset: 0, enabled: true }
+		);
+
+		assert_noop!(
+			OcwDemo::set_task_schedule(Origin::signed(who), OffchainTask::Prices, schedule),
+			BadOrigin
+		);
+		assert_noop!(
+			OcwDemo::set_task_schedule(
+				Origin::root(),
+				OffchainTask::Prices,
+				TaskSchedule { interval: 0, ..schedule }
+			),
+			Error::<Test>::InvalidTaskSchedule
+		);
+		assert_ok!(OcwDemo::set_task_schedule(Origin::root(), OffchainTask::Prices, schedule));
+		assert_eq!(OcwDemo::task_schedule(OffchainTask::Prices), schedule);
+		assert_eq!(
+			System::events().pop().unwrap().event,
+			Event::OcwDemo(crate::Event::TaskScheduleSet(OffchainTask::Prices, schedule))
+		);
+	});
+}
+
+#[test]
+fn task_schedules_follow_interval_and_offset() {
+	let schedule = TaskSchedule { interval: 3u64, offset: 7, enabled: true };
+	assert!(schedule.is_due(1));
+	assert!(schedule.is_due(4));
+	assert!(!schedule.is_due(5));
+	assert!(!TaskSchedule { enabled: false, ..schedule }.is_due(4));
+	assert!(!TaskSchedule { interval: 0, ..schedule }.is_due(4));
+}
+
+#[test]
+fn offchain_worker_skips_paused_tasks() {
+	let (mut t, handles) = new_offchain_test_ext();
+	t.execute_with(|| {
+		let paused =
+			TaskSchedule { enabled: false, ..OcwDemo::task_schedule(OffchainTask::SignedNumber) };
+		assert_ok!(OcwDemo::set_task_schedule(Origin::root(), OffchainTask::SignedNumber, paused));
+
+		OcwDemo::offchain_worker(6);
+		assert!(handles.pool.read().transactions.is_empty());
+	});
+}
+
+#[test]
+fn offchain_worker_runs_every_due_task() {
+	let (mut t, handles) = new_offchain_test_ext();
+	expect_get(&handles.offchain, COINCAP_DOT, COINCAP_DOT_RESPONSE);
+
+	t.execute_with(|| {
+		register(b"DOT", b"polkadot");
+		let every_block = TaskSchedule { interval: 1, offset: 0, enabled: true };
+		assert_ok!(OcwDemo::set_task_schedule(
+			Origin::root(),
+			OffchainTask::SignedNumber,
+			every_block
+		));
+
+		OcwDemo::offchain_worker(10);
+
+		let calls = handles
+			.pool
+			.write()
+			.transactions
+			.drain(..)
+			.map(|tx| Extrinsic::decode(&mut &*tx).unwrap().call)
+			.collect::<Vec<_>>();
+		assert_eq!(calls.len(), 2);
+		assert_eq!(calls[0], Call::OcwDemo(OcwCall::submit_number_signed(10)));
+		assert!(matches!(
+			calls[1],
+			Call::OcwDemo(OcwCall::submit_price_unsigned_with_signed_payload(..))
+		));
+	});
+}
+
+const INDEXED_URL: &str = "https://example.com/data.json";
+
+fn indexed_url() -> BoundedVec<u8, MaxUrlLength> {
+	INDEXED_URL.as_bytes().to_vec().try_into().unwrap()
+}
+
+/// Request `INDEXED_URL` and store its `IndexingData` the way an indexing node would.
+fn request_indexing(who: Public) -> u64 {
+	let id = crate::NextIndexingId::<Test>::get();
+	assert_ok!(OcwDemo::request_indexing(Origin::signed(who), indexed_url()));
+	sp_io::offchain::local_storage_set(
+		StorageKind::PERSISTENT,
+		&OcwDemo::indexing_key(System::block_number(), id),
+		&IndexingData(INDEXED_URL.as_bytes().to_vec(), id).encode(),
+	);
+	id
+}
+
+#[test]
+fn request_indexing_queues_request() {
+	let (mut t, handles) = new_offchain_test_ext();
+	t.execute_with(|| {
+		System::set_block_number(3);
+
+		assert_ok!(OcwDemo::request_indexing(Origin::signed(handles.public), indexed_url()));
+		assert_ok!(OcwDemo::request_indexing(Origin::root(), indexed_url()));
+		assert_eq!(OcwDemo::pending_indexing(0), Some(3));
+		assert_eq!(OcwDemo::pending_indexing(1), Some(3));
+		assert_eq!(
+			System::events().pop().unwrap().event,
+			Event::OcwDemo(crate::Event::IndexingRequested(None, 1))
+		);
+
+		// The queue is full until the offchain worker processes a request.
+		assert_noop!(
+			OcwDemo::request_indexing(Origin::root(), indexed_url()),
+			Error::<Test>::TooManyIndexingRequests
+		);
+		let result = IndexingResult { length: 0, hash: [0; 32] };
+		assert_ok!(OcwDemo::submit_indexing_result_unsigned_with_signed_payload(
+			Origin::none(),
+			PayloadIndexing { id: 0, result, public: handles.public },
+			sp_core::sr25519::Signature::from_raw([0u8; 64]),
+		));
+		assert_ok!(OcwDemo::request_indexing(Origin::root(), indexed_url()));
+	});
+}
+
+#[test]
+fn request_indexing_is_restricted_to_oracles_and_http_urls() {
+	let (mut t, handles) = new_offchain_test_ext();
+	t.execute_with(|| {
+		let who = sp_core::sr25519::Public::from_raw([1u8; 32]);
+		assert_noop!(
+			OcwDemo::request_indexing(Origin::signed(who), indexed_url()),
+			Error::<Test>::NotAnOracle
+		);
+		assert_noop!(OcwDemo::request_indexing(Origin::none(), indexed_url()), BadOrigin);
+
+		let file = b"file:///etc/passwd".to_vec().try_into().unwrap();
+		assert_noop!(
+			OcwDemo::request_indexing(Origin::signed(handles.public), file),
+			Error::<Test>::InvalidUrl
+		);
+
+		// URLs longer than `MaxUrlLength` do not even decode.
+		let long = vec![b'a'; MaxUrlLength::get() as usize + 1];
+		let call = OcwCall::<Test>::request_indexing(indexed_url()).encode();
+		let mut encoded = call[..call.len() - (INDEXED_URL.len() + 1)].to_vec();
+		encoded.extend(long.encode());
+		assert!(OcwCall::<Test>::decode(&mut &*encoded).is_err());
+	});
+}
+
+#[test]
+fn offchain_worker_processes_indexed_data() {
+	let (mut t, handles) = new_offchain_test_ext();
+	let body = br#"{"hello":"world"}"#;
+	expect_get(&handles.offchain, INDEXED_URL, body);
+
+	t.execute_with(|| {
+		let id = request_indexing(handles.public);
+
+		OcwDemo::offchain_worker(9);
+
+		let result =
+			IndexingResult { length: body.len() as u32, hash: sp_io::hashing::blake2_256(body) };
+		let (payload, signature) = match pop_extrinsic(&handles).call {
+			Call::OcwDemo(OcwCall::submit_indexing_result_unsigned_with_signed_payload(
+				payload,
+				signature,
+			)) => (payload, signature),
+			call => panic!("unexpected call: {:?}", call),
+		};
+		assert_eq!(payload, PayloadIndexing { id, result, public: handles.public });
+
+		// The result is not fetched again while the submission may still be included.
+		OcwDemo::offchain_worker(10);
+		assert!(handles.pool.read().transactions.is_empty());
+
+		assert_ok!(OcwDemo::submit_indexing_result_unsigned_with_signed_payload(
+			Origin::none(),
+			payload.clone(),
+			signature.clone()
+		));
+		assert_eq!(OcwDemo::indexing_results(id), Some(result));
+		assert_eq!(OcwDemo::pending_indexing(id), None);
+
+		let call = OcwCall::submit_indexing_result_unsigned_with_signed_payload(payload, signature);
+		assert_eq!(
+			OcwDemo::validate_unsigned(TransactionSource::External, &call),
+			InvalidTransaction::Custom(ValidityError::UnknownRequest as u8).into(),
+		);
+	});
+}
+
+#[test]
+fn offchain_worker_skips_requests_without_indexing_data() {
+	let (mut t, handles) = new_offchain_test_ext();
+	t.execute_with(|| {
+		assert_ok!(OcwDemo::request_indexing(Origin::signed(handles.public), indexed_url()));
+
+		OcwDemo::offchain_worker(9);
+		assert!(handles.pool.read().transactions.is_empty());
+		assert_eq!(OcwDemo::pending_indexing(0), Some(1));
+	});
+}
+
+#[test]
+fn offchain_worker_submits_signed_number_on_block_mod_1() {
+	let (mut t, handles) = new_offchain_test_ext();
+	t.execute_with(|| {
+		OcwDemo::offchain_worker(6);
+
+		let tx = pop_extrinsic(&handles);
+		assert_eq!(tx.signature, Some((0, ())));
+		assert_eq!(tx.call, Call::OcwDemo(OcwCall::submit_number_signed(6)));
+	});
+}
+
+#[test]
+fn offchain_worker_submits_unsigned_number_on_block_mod_2() {
+	let (mut t, handles) = new_offchain_test_ext();
+	t.execute_with(|| {
+		OcwDemo::offchain_worker(7);
+
+		let tx = pop_extrinsic(&handles);
+		assert_eq!(tx.signature, None);
+		assert_eq!(tx.call, Call::OcwDemo(OcwCall::submit_number_unsigned(7)));
+	});
+}
+
+#[test]
+fn offchain_worker_submits_number_with_signed_payload_on_block_mod_3() {
+	let (mut t, handles) = new_offchain_test_ext();
+	t.execute_with(|| {
+		OcwDemo::offchain_worker(8);
+
+		let tx = pop_extrinsic(&handles);
+		assert_eq!(tx.signature, None);
+		if let Call::OcwDemo(OcwCall::submit_number_unsigned_with_signed_payload(body, signature)) =
+			tx.call
+		{
+			assert_eq!(body, Payload { number: 8, public: handles.public });
+			assert!(<Payload<Public> as SignedPayload<Test>>::verify::<crate::crypto::TestAuthId>(
+				&body, signature
+			));
+		} else {
+			panic!("unexpected call: {:?}", tx.call);
+		}
+	});
+}
+
+#[test]
+fn offchain_worker_caches_github_info_on_block_mod_4() {
+	let (mut t, handles) = new_offchain_test_ext();
+	expect_get(&handles.offchain, GITHUB_ORG, GITHUB_ORG_RESPONSE);
+
+	t.execute_with(|| {
+		set_feed(GITHUB_FEED_ID, feed(GITHUB_ORG, ""));
+		OcwDemo::offchain_worker(9);
+
+		assert!(handles.pool.read().transactions.is_empty());
+		let cached = sp_io::offchain::local_storage_get(
+			StorageKind::PERSISTENT,
+			&crate::cache::cache_key(GITHUB_FEED_ID),
+		)
+		.expect("github info is cached");
+		let entry =
+			<CacheEntry<(Vec<u8>, Vec<u8>, u32), u64>>::decode(&mut &cached[..]).unwrap();
+		let (login, blog, public_repos) = entry.value;
+		assert_eq!(login, b"substrate-developer-hub".to_vec());
+		assert_eq!(blog, b"https://substrate.dev".to_vec());
+		assert_eq!(public_repos, 42);
+		assert_eq!((entry.fetched_at, entry.block_number), (0, 9));
+
+		// Served from the cache: no further request is expected.
+		OcwDemo::offchain_worker(14);
+	});
+}
+
+#[test]
+fn offchain_cache_entries_go_stale_then_expire() {
+	let entry = CacheEntry { value: (), fetched_at: 1_000, block_number: 1u64 };
+	assert_eq!(entry.freshness(1_000, 10, 5), Freshness::Fresh);
+	assert_eq!(entry.freshness(1_009, 10, 5), Freshness::Fresh);
+	assert_eq!(entry.freshness(1_010, 10, 5), Freshness::Stale);
+	assert_eq!(entry.freshness(1_015, 10, 5), Freshness::Expired);
+	// A clock running backwards does not expire anything.
+	assert_eq!(entry.freshness(0, 10, 5), Freshness::Fresh);
+}
+
+#[test]
+fn offchain_cache_falls_back_to_stale_values() {
+	let (mut t, _) = new_offchain_test_ext();
+	t.execute_with(|| {
+		let cache = OffchainCache::<u64>::new(b"test", 10, 5);
+		let fail = || Err::<u32, _>("offline");
+		assert_eq!(cache.get_or_fetch(0, 1, fail), Err("offline"));
+		assert_eq!(cache.get_or_fetch(0, 1, || Ok::<u32, &str>(7)), Ok(7));
+		assert_eq!(cache.entry(), Some(CacheEntry { value: 7u32, fetched_at: 0, block_number: 1 }));
+
+		// Fresh values are not fetched again.
+		assert_eq!(cache.get_or_fetch(9, 2, || Ok::<u32, &str>(8)), Ok(7));
+		assert_eq!(cache.get_or_fetch(10, 2, fail), Ok(7));
+		assert_eq!(cache.lookup::<u32>(10), Cached::Stale(7));
+		assert_eq!(cache.get_or_fetch(15, 3, fail), Err("offline"));
+		assert_eq!(cache.get_or_fetch(15, 3, || Ok::<u32, &str>(8)), Ok(8));
+		assert_eq!(cache.lookup::<u32>(15), Cached::Fresh(8));
+		assert_eq!(cache.lookup::<u32>(25), Cached::Stale(8));
+		assert_eq!(cache.lookup::<u32>(30), Cached::Missing);
+	});
+}
+
+#[test]
+fn invalidate_cache_is_root_only() {
+	new_test_ext().execute_with(|| {
+		System::set_block_number(1);
+		let who = sp_core::sr25519::Public::from_raw([1u8; 32]);
+		assert_noop!(OcwDemo::invalidate_cache(Origin::signed(who), b"github".to_vec()), BadOrigin);
+		assert_noop!(
+			OcwDemo::invalidate_cache(Origin::root(), vec![b'x'; 17]),
+			Error::<Test>::FeedIdTooLong
+		);
+
+		assert_ok!(OcwDemo::invalidate_cache(Origin::root(), b"github".to_vec()));
+		assert_eq!(
+			System::events().pop().unwrap().event,
+			Event::OcwDemo(crate::Event::CacheInvalidated(b"github".to_vec()))
+		);
+	});
+}
+
+#[test]
+fn offchain_worker_reuses_cached_prices() {
+	let (mut t, handles) = new_offchain_test_ext();
+	expect_get(&handles.offchain, COINCAP_DOT, COINCAP_DOT_RESPONSE);
+
+	t.execute_with(|| {
+		register(b"DOT", b"polkadot");
+		let submitted_price = |handles: &OffchainHandles| match pop_extrinsic(handles).call {
+			Call::OcwDemo(OcwCall::submit_price_unsigned_with_signed_payload(body, _)) =>
+				body.price_tuple,
+			call => panic!("unexpected call: {:?}", call),
+		};
+		let dot = (28, Permill::from_parts(123457));
+
+		OcwDemo::offchain_worker(10);
+		assert_eq!(submitted_price(&handles), dot);
+
+		// Still fresh: no request is expected.
+		OcwDemo::offchain_worker(15);
+		assert_eq!(submitted_price(&handles), dot);
+
+		// Stale: fetched again, and the cached price stands in for the failed request.
+		handles.offchain.write().timestamp = Timestamp::from_unix_millis(60_000);
+		expect_get(&handles.offchain, COINCAP_DOT, br#"{"error":"rate limited"}"#);
+		OcwDemo::offchain_worker(20);
+		assert_eq!(submitted_price(&handles), dot);
+
+		// Expired: nothing is left to submit.
+		handles.offchain.write().timestamp = Timestamp::from_unix_millis(120_000);
+		expect_get(&handles.offchain, COINCAP_DOT, br#"{"error":"rate limited"}"#);
+		OcwDemo::offchain_worker(25);
+		assert!(handles.pool.read().transactions.is_empty());
+	});
+}
+
+fn retry_policy() -> RetryPolicy {
+	RetryPolicy {
+		max_attempts: 3,
+		backoff: 100,
+		retry_statuses: vec![429, 503],
+		failure_threshold: 2,
+		cooldown: 1_000,
+	}
+}
+
+#[test]
+fn retry_policy_retries_transient_failures_with_growing_backoff() {
+	let policy = retry_policy();
+	assert!(policy.should_retry(&HttpFailure::IoError));
+	assert!(policy.should_retry(&HttpFailure::Status(429)));
+	assert!(policy.should_retry(&HttpFailure::Status(503)));
+	assert!(!policy.should_retry(&HttpFailure::Status(404)));
+	assert!(!policy.should_retry(&HttpFailure::DeadlineReached));
+	assert!(!policy.should_retry(&HttpFailure::CoolingDown));
+
+	assert_eq!(policy.backoff_after(1), 100);
+	assert_eq!(policy.backoff_after(2), 200);
+	assert_eq!(policy.backoff_after(3), 400);
+	assert_eq!(policy.backoff_after(100), u64::MAX);
+}
+
+#[test]
+fn endpoint_health_cools_down_after_repeated_failures() {
+	let policy = retry_policy();
+	let mut health = EndpointHealth::default();
+	assert!(!health.is_cooling_down(0));
+
+	health.record_failure(HttpFailure::IoError, 10, &policy);
+	assert_eq!(health.consecutive_failures, 1);
+	assert!(!health.is_cooling_down(10));
+
+	health.record_failure(HttpFailure::Status(503), 20, &policy);
+	assert_eq!(health.last_error, Some(HttpFailure::Status(503)));
+	assert!(health.is_cooling_down(1_019));
+	assert!(!health.is_cooling_down(1_020));
+}
+
+#[test]
+fn offchain_worker_skips_cooling_down_endpoints() {
+	let (mut t, handles) = new_offchain_test_ext();
+	t.execute_with(|| {
+		register(b"DOT", b"polkadot");
+		let health = EndpointHealth {
+			consecutive_failures: 2,
+			last_error: Some(HttpFailure::Status(503)),
+			cooldown_until: 30_000,
+		};
+		sp_io::offchain::local_storage_set(
+			StorageKind::PERSISTENT,
+			&endpoint_key(COINCAP_DOT.as_bytes()),
+			&health.encode(),
+		);
+
+		// No request is expected while the endpoint cools down.
+		OcwDemo::offchain_worker(10);
+		assert!(handles.pool.read().transactions.is_empty());
+		assert_eq!(OcwDemo::endpoint_health(COINCAP_DOT.as_bytes()), health);
+
+		handles.offchain.write().timestamp = Timestamp::from_unix_millis(30_000);
+		expect_get(&handles.offchain, COINCAP_DOT, COINCAP_DOT_RESPONSE);
+		OcwDemo::offchain_worker(15);
+		pop_extrinsic(&handles);
+		assert_eq!(OcwDemo::endpoint_health(COINCAP_DOT.as_bytes()), EndpointHealth::default());
+	});
+}
+
+#[test]
+fn offchain_worker_submits_dot_price_on_block_mod_0() {
+	let (mut t, handles) = new_offchain_test_ext();
+	expect_get(&handles.offchain, COINCAP_DOT, COINCAP_DOT_RESPONSE);
+
+	t.execute_with(|| {
+		register(b"DOT", b"polkadot");
+		OcwDemo::offchain_worker(10);
+
+		let tx = pop_extrinsic(&handles);
+		assert_eq!(tx.signature, None);
+		if let Call::OcwDemo(OcwCall::submit_price_unsigned_with_signed_payload(body, signature)) =
+			tx.call
+		{
+			assert_eq!(body.symbol, b"DOT".to_vec());
+			assert_eq!(body.block_number, 10);
+			assert_eq!(body.price_tuple, (28, Permill::from_parts(123457)));
+			assert_eq!(body.public, handles.public);
+			assert!(<PayloadPrice<Public, u64> as SignedPayload<Test>>::verify::<
+				crate::crypto::TestAuthId,
+			>(&body, signature));
+		} else {
+			panic!("unexpected call: {:?}", tx.call);
+		}
+	});
+}
+
+#[test]
+fn offchain_worker_commits_then_reveals_in_commit_reveal_mode() {
+	let (mut t, handles) = new_offchain_test_ext();
+	expect_get(&handles.offchain, COINCAP_DOT, COINCAP_DOT_RESPONSE);
+
+	t.execute_with(|| {
+		register(b"DOT", b"polkadot");
+		assert_ok!(OcwDemo::set_submission_mode(Origin::root(), SubmissionMode::CommitReveal));
+		System::set_block_number(10);
+		OcwDemo::offchain_worker(10);
+
+		let commitment = match pop_extrinsic(&handles).call {
+			Call::OcwDemo(OcwCall::commit_price_unsigned_with_signed_payload(body, _)) => {
+				assert_eq!(body.block_number, 10);
+				body.commitment
+			},
+			call => panic!("unexpected call: {:?}", call),
+		};
+		let pending = StorageValueRef::persistent(REVEALS_KEY).get::<PendingRevealsOf<Test>>();
+		assert_eq!(pending.unwrap().unwrap().len(), 1);
+
+		// The reveal goes out once it lands in the reveal window.
+		OcwDemo::offchain_worker(10 + CommitPeriod::get() - 1);
+		let reveal = handles
+			.pool
+			.write()
+			.transactions
+			.drain(..)
+			.map(|tx| Extrinsic::decode(&mut &*tx).unwrap().call)
+			.find_map(|call| match call {
+				Call::OcwDemo(OcwCall::reveal_price_unsigned_with_signed_payload(body, _)) =>
+					Some(body),
+				_ => None,
+			})
+			.expect("a reveal was submitted");
+		assert_eq!(reveal.block_number, 10);
+		assert_eq!(reveal.price_tuple, (28, Permill::from_parts(123457)));
+		assert_eq!(
+			OcwDemo::commitment(&handles.public, reveal.price_tuple, &reveal.salt),
+			commitment
+		);
+		let pending = StorageValueRef::persistent(REVEALS_KEY).get::<PendingRevealsOf<Test>>();
+		assert_eq!(pending.unwrap(), Some(vec![]));
+	});
+}
+
+#[test]
+fn offchain_worker_fetches_every_tracked_asset() {
+	let (mut t, handles) = new_offchain_test_ext();
+	t.execute_with(|| {
+		register(b"DOT", b"polkadot");
+		register(b"KSM", b"kusama");
+		// Requests are matched in the order they are sent, which follows the map order.
+		for key in TrackedAssets::<Test>::iter_keys() {
+			match &key[..] {
+				b"DOT" => expect_get(&handles.offchain, COINCAP_DOT, COINCAP_DOT_RESPONSE),
+				_ => expect_get(&handles.offchain, COINCAP_KSM, COINCAP_KSM_RESPONSE),
+			}
+		}
+
+		OcwDemo::offchain_worker(10);
+
+		let mut prices = handles
+			.pool
+			.write()
+			.transactions
+			.drain(..)
+			.map(|tx| match Extrinsic::decode(&mut &*tx).unwrap().call {
+				Call::OcwDemo(OcwCall::submit_price_unsigned_with_signed_payload(body, _)) =>
+					(body.symbol, body.price_tuple),
+				call => panic!("unexpected call: {:?}", call),
+			})
+			.collect::<Vec<_>>();
+		prices.sort();
+		assert_eq!(
+			prices,
+			vec![
+				(b"DOT".to_vec(), (28, Permill::from_parts(123457))),
+				(b"KSM".to_vec(), (301, Permill::from_parts(500_000))),
+			]
+		);
+	});
+}
+
+#[test]
+fn offchain_worker_follows_feed_method_pointer_and_decimals() {
+	let (mut t, handles) = new_offchain_test_ext();
+	handles.offchain.write().expect_request(PendingRequest {
+		method: "POST".into(),
+		uri: "https://oracle.example.com/quote".into(),
+		headers: vec![("Accept".into(), "application/json".into())],
+		response: Some(br#"{"result":{"price":2812345678}}"#.to_vec()),
+		sent: true,
+		..Default::default()
+	});
+
+	t.execute_with(|| {
+		set_feed(
+			b"example",
+			FeedConfig {
+				url: bounded(b"https://oracle.example.com/quote"),
+				method: HttpMethod::Post,
+				headers: headers(&[(b"Accept", b"application/json")]),
+				json_pointer: bounded(b"/result/price"),
+				decimals: 8,
+			},
+		);
+		assert_ok!(OcwDemo::register_asset(
+			Origin::root(),
+			b"DOT".to_vec(),
+			vec![b"example".to_vec()]
+		));
+
+		OcwDemo::offchain_worker(10);
+
+		match pop_extrinsic(&handles).call {
+			Call::OcwDemo(OcwCall::submit_price_unsigned_with_signed_payload(body, _)) =>
+				assert_eq!(body.price_tuple, (28, Permill::from_parts(123457))),
+			call => panic!("unexpected call: {:?}", call),
+		}
+	});
+}
+
+#[test]
+fn offchain_worker_ignores_failed_and_removed_feeds() {
+	let (mut t, handles) = new_offchain_test_ext();
+	expect_get(&handles.offchain, COINCAP_KSM, br#"{"error":"rate limited"}"#);
+	expect_get(&handles.offchain, COINCAP_DOT, COINCAP_DOT_RESPONSE);
+
+	t.execute_with(|| {
+		set_feed(b"first", feed(COINCAP_KSM, "/data/priceUsd"));
+		set_feed(b"removed", feed(COINCAP_KSM, "/data/priceUsd"));
+		set_feed(b"second", feed(COINCAP_DOT, "/data/priceUsd"));
+		assert_ok!(OcwDemo::register_asset(
+			Origin::root(),
+			b"DOT".to_vec(),
+			vec![b"first".to_vec(), b"removed".to_vec(), b"second".to_vec()]
+		));
+		assert_ok!(OcwDemo::remove_feed(Origin::root(), b"removed".to_vec()));
+
+		OcwDemo::offchain_worker(10);
+
+		match pop_extrinsic(&handles).call {
+			Call::OcwDemo(OcwCall::submit_price_unsigned_with_signed_payload(body, _)) => {
+				assert_eq!(body.price_tuple, (28, Permill::from_parts(123457)));
+				assert_eq!(body.sources, 1);
+			},
+			call => panic!("unexpected call: {:?}", call),
+		}
+	});
+}
+
+#[test]
+fn offchain_worker_submits_median_of_agreeing_feeds() {
+	let (mut t, handles) = new_offchain_test_ext();
+	let quotes = [
+		("https://a.example.com/dot", &br#"{"price":"28.2"}"#[..]),
+		("https://b.example.com/dot", &br#"{"price":"50"}"#[..]),
+		("https://c.example.com/dot", &br#"{"price":"28.1"}"#[..]),
+	];
+	for (url, response) in quotes.iter() {
+		expect_get(&handles.offchain, url, response);
+	}
+
+	t.execute_with(|| {
+		for (i, (url, _)) in quotes.iter().enumerate() {
+			set_feed(&[b'a' + i as u8], feed(url, "/price"));
+		}
+		assert_ok!(OcwDemo::register_asset(
+			Origin::root(),
+			b"DOT".to_vec(),
+			vec![b"a".to_vec(), b"b".to_vec(), b"c".to_vec()]
+		));
+
+		OcwDemo::offchain_worker(10);
+
+		match pop_extrinsic(&handles).call {
+			Call::OcwDemo(OcwCall::submit_price_unsigned_with_signed_payload(body, _)) => {
+				// `50` is more than 10% away from the median of `28.2` and is dropped.
+				assert_eq!(body.price_tuple, (28, Permill::from_parts(150_000)));
+				assert_eq!(body.sources, 2);
+			},
+			call => panic!("unexpected call: {:?}", call),
+		}
+	});
+}
+
+#[test]
+fn offchain_worker_survives_malformed_responses() {
+	let (mut t, handles) = new_offchain_test_ext();
+	let responses = [
+		("https://a.example.com/dot", &b"\xff\xfe"[..]),
+		("https://b.example.com/dot", &b"<html>busy</html>"[..]),
+		("https://c.example.com/dot", &br#"{"data":{}}"#[..]),
+		("https://d.example.com/dot", &br#"{"data":{"priceUsd":"n/a"}}"#[..]),
+	];
+	for (url, response) in responses.iter() {
+		expect_get(&handles.offchain, url, response);
+	}
+
+	t.execute_with(|| {
+		let ids = [b"a".to_vec(), b"b".to_vec(), b"c".to_vec(), b"d".to_vec()];
+		for (id, (url, _)) in ids.iter().zip(responses.iter()) {
+			set_feed(id, feed(url, "/data/priceUsd"));
+		}
+		assert_ok!(OcwDemo::register_asset(Origin::root(), b"DOT".to_vec(), ids.to_vec()));
+
+		OcwDemo::offchain_worker(10);
+		assert!(handles.pool.read().transactions.is_empty());
+	});
+}
+
+#[test]
+fn offchain_errors_tell_failures_apart() {
+	let messages = [
+		(OffchainError::Http(HttpFailure::DeadlineReached), "timeout"),
+		(OffchainError::Http(HttpFailure::Status(429)), "http status 429"),
+		(OffchainError::InvalidUtf8, "response is not utf-8"),
+		(OffchainError::InvalidJson, "response is not json"),
+		(
+			OffchainError::JsonShape { path: b"/data/priceUsd".to_vec() },
+			"no string or number at json pointer `/data/priceUsd`",
+		),
+		(
+			OffchainError::ParseNumber { value: b"n/a".to_vec(), error: ParseError::InvalidFormat },
+			"cannot parse `n/a` as a price: not a decimal number",
+		),
+		(
+			OffchainError::NotEnoughSources { agreed: 1, required: 2 },
+			"1 feeds agreed on a price, 2 required",
+		),
+	];
+	for (error, message) in messages.iter() {
+		assert_eq!(error.to_string(), *message);
+	}
+}
+
+#[test]
+fn prices_backed_by_too_few_sources_are_rejected() {
+	let (mut t, handles) = new_offchain_test_ext();
+	t.execute_with(|| {
+		register(b"DOT", b"polkadot");
+		let payload = PayloadPrice {
+			symbol: b"DOT".to_vec(),
+			block_number: 1,
+			price_tuple: (28, Permill::zero()),
+			sources: 0,
+			public: handles.public,
+		};
+		let signature = <PayloadPrice<Public, u64> as SignedPayload<Test>>::sign::<
+			crate::crypto::TestAuthId,
+		>(&payload)
+		.unwrap();
+		let call =
+			OcwCall::submit_price_unsigned_with_signed_payload(payload.clone(), signature.clone());
+		assert_eq!(
+			OcwDemo::validate_unsigned(TransactionSource::External, &call),
+			InvalidTransaction::Call.into(),
+		);
+		assert_noop!(
+			OcwDemo::submit_price_unsigned_with_signed_payload(Origin::none(), payload, signature),
+			Error::<Test>::NotEnoughSources
+		);
+	});
+}
+
+fn price(integer: u64, parts: u32) -> (u64, Permill) {
+	(integer, Permill::from_parts(parts))
+}
+
+#[test]
+fn median_takes_the_middle_or_the_mean_of_the_two_middles() {
+	assert_eq!(median(&[]), None);
+	assert_eq!(median(&[price(3, 0), price(1, 0), price(2, 0)]), Some(price(2, 0)));
+	assert_eq!(median(&[price(2, 0), price(1, 0)]), Some(price(1, 500_000)));
+	assert_eq!(median(&[price(0, 1), price(0, 2)]), Some(price(0, 1)));
+}
+
+#[test]
+fn time_weighted_mean_weighs_prices_by_how_long_they_held() {
+	assert_eq!(time_weighted_mean(&[]), None);
+	assert_eq!(time_weighted_mean(&[(price(7, 0), 5)]), Some(price(7, 0)));
+	// The latest price has not held yet.
+	assert_eq!(
+		time_weighted_mean(&[(price(10, 0), 0), (price(20, 0), 30), (price(90, 0), 40)]),
+		Some(price(12, 500_000))
+	);
+	// Without elapsed time every price weighs the same.
+	assert_eq!(time_weighted_mean(&[(price(10, 0), 5), (price(20, 0), 5)]), Some(price(15, 0)));
+}
+
+#[test]
+fn ema_moves_towards_the_new_price() {
+	let quarter = Permill::from_percent(25);
+	assert_eq!(ema(None, price(8, 0), quarter), price(8, 0));
+	assert_eq!(ema(Some(price(8, 0)), price(16, 0), quarter), price(10, 0));
+	assert_eq!(ema(Some(price(8, 0)), price(16, 0), Permill::one()), price(16, 0));
+	assert_eq!(ema(Some(price(8, 0)), price(16, 0), Permill::zero()), price(8, 0));
+}
+
+#[test]
+fn mean_rounds_down() {
+	assert_eq!(mean(&[]), None);
+	assert_eq!(mean(&[price(1, 0), price(2, 0)]), Some(price(1, 500_000)));
+	assert_eq!(mean(&[price(0, 1), price(0, 1), price(0, 2)]), Some(price(0, 1)));
+}
+
+#[test]
+fn aggregate_drops_outliers() {
+	let ten = Permill::from_percent(10);
+	assert_eq!(aggregate(&[], ten), None);
+	assert_eq!(aggregate(&[price(100, 0)], ten), Some((price(100, 0), 1)));
+	assert_eq!(
+		aggregate(&[price(100, 0), price(105, 0), price(95, 0), price(1, 0), price(500, 0)], ten),
+		Some((price(100, 0), 3))
+	);
+	// The bound is inclusive.
+	assert_eq!(
+		aggregate(&[price(100, 0), price(110, 0), price(90, 0)], ten),
+		Some((price(100, 0), 3))
+	);
+	// No two sources agree.
+	assert_eq!(aggregate(&[price(1, 0), price(100, 0)], ten), None);
+}
+
+#[test]
+fn deviates_is_relative_to_the_reference() {
+	let five = Permill::from_percent(5);
+	assert!(!deviates(price(100, 0), price(100, 0), five));
+	assert!(!deviates(price(105, 0), price(100, 0), five));
+	assert!(!deviates(price(95, 0), price(100, 0), five));
+	assert!(deviates(price(105, 1), price(100, 0), five));
+	assert!(deviates(price(94, 999_999), price(100, 0), five));
+	assert!(deviates(price(0, 1), price(0, 0), five));
+}
+
+#[test]
+fn parse_price_accepts_any_number_of_decimals() {
+	assert_eq!(parse_price("7", 0), Ok((7, Permill::zero())));
+	assert_eq!(parse_price("7.", 0), Ok((7, Permill::zero())));
+	assert_eq!(parse_price(".5", 0), Ok((0, Permill::from_parts(500_000))));
+	assert_eq!(parse_price("7.12", 0), Ok((7, Permill::from_parts(120_000))));
+	assert_eq!(parse_price("7.000012", 0), Ok((7, Permill::from_parts(12))));
+	assert_eq!(parse_price("0007.5", 0), Ok((7, Permill::from_parts(500_000))));
+}
+
+#[test]
+fn parse_price_rounds_half_up() {
+	assert_eq!(parse_price("7.1234564", 0), Ok((7, Permill::from_parts(123_456))));
+	assert_eq!(parse_price("7.1234565", 0), Ok((7, Permill::from_parts(123_457))));
+	assert_eq!(parse_price("0.9999996", 0), Ok((1, Permill::zero())));
+	assert_eq!(parse_price("0.0000004", 0), Ok((0, Permill::zero())));
+}
+
+#[test]
+fn parse_price_handles_exponents_and_implied_decimals() {
+	assert_eq!(parse_price("1.5e2", 0), Ok((150, Permill::zero())));
+	assert_eq!(parse_price("2.5E-3", 0), Ok((0, Permill::from_parts(2_500))));
+	assert_eq!(parse_price("+1e+1", 0), Ok((10, Permill::zero())));
+	assert_eq!(parse_price("1e-100000000", 0), Ok((0, Permill::zero())));
+	assert_eq!(parse_price("712345", 5), Ok((7, Permill::from_parts(123_450))));
+	assert_eq!(parse_price("28123456789", 9), Ok((28, Permill::from_parts(123_457))));
+}
+
+#[test]
+fn parse_price_rejects_malformed_input() {
+	assert_eq!(parse_price("", 0), Err(ParseError::Empty));
+	assert_eq!(parse_price("  ", 0), Err(ParseError::Empty));
+	assert_eq!(parse_price("-", 0), Err(ParseError::Empty));
+	assert_eq!(parse_price(".", 0), Err(ParseError::InvalidFormat));
+	assert_eq!(parse_price("abc", 0), Err(ParseError::InvalidFormat));
+	assert_eq!(parse_price("1.2.3", 0), Err(ParseError::InvalidFormat));
+	assert_eq!(parse_price("1e", 0), Err(ParseError::InvalidFormat));
+	assert_eq!(parse_price("1e1.5", 0), Err(ParseError::InvalidFormat));
+	assert_eq!(parse_price("1,5", 0), Err(ParseError::InvalidFormat));
+	assert_eq!(parse_price("-7.12", 0), Err(ParseError::Negative));
+	assert_eq!(parse_price("-0.000", 0), Ok((0, Permill::zero())));
+	assert_eq!(parse_price("18446744073709551616", 0), Err(ParseError::Overflow));
+	assert_eq!(parse_price("1e400", 0), Err(ParseError::Overflow));
+	assert_eq!(parse_price("18446744073709551615.9999999", 0), Err(ParseError::Overflow));
+}
+
+fn pow10(exp: u32) -> Option<u128> {
+	10u128.checked_pow(exp)
+}
+
+proptest! {
+	#[test]
+	fn parse_price_roundtrips_six_decimals(integer in any::<u64>(), fraction in 0u32..1_000_000) {
+		let input = format!("{}.{:06}", integer, fraction);
+		prop_assert_eq!(parse_price(&input, 0), Ok((integer, Permill::from_parts(fraction))));
+	}
+
+	#[test]
+	fn parse_scaled_rounds_extra_digits(
+		integer in any::<u32>(),
+		fraction in 0u32..1_000_000,
+		tail in "[0-9]{0,20}",
+	) {
+		let input = format!("{}.{:06}{}", integer, fraction, tail);
+		let round_up = tail.bytes().next().map_or(false, |d| d >= b'5');
+		let expected = integer as u128 * 1_000_000 + fraction as u128 + round_up as u128;
+		prop_assert_eq!(parse_scaled(&input, 6), Ok(expected));
+	}
+
+	#[test]
+	fn parse_scaled_ignores_leading_zeros(value in any::<u64>(), zeros in "0{0,30}") {
+		let input = format!("{}{}", zeros, value);
+		prop_assert_eq!(parse_scaled(&input, 6), parse_scaled(&value.to_string(), 6));
+	}
+
+	#[test]
+	fn parse_scaled_matches_scientific_notation(mantissa in any::<u64>(), exp in -30i32..30) {
+		let shift = exp + 6;
+		let expected = if shift >= 0 {
+			pow10(shift as u32)
+				.and_then(|p| (mantissa as u128).checked_mul(p))
+				.ok_or(ParseError::Overflow)
+		} else {
+			let divisor = pow10((-shift) as u32).unwrap();
+			let quotient = mantissa as u128 / divisor;
+			let remainder = mantissa as u128 % divisor;
+			Ok(quotient + (remainder * 2 >= divisor) as u128)
+		};
+		prop_assert_eq!(parse_scaled(&format!("{}e{}", mantissa, exp), 6), expected);
+	}
+
+	#[test]
+	fn parse_scaled_never_panics(input in "\\PC*", scale in -40i32..40) {
+		let _ = parse_scaled(&input, scale);
+	}
+
+	#[test]
+	fn aggregate_lies_between_the_extremes(
+		prices in prop::collection::vec((0u64..1_000, 0u32..1_000_000), 1..10),
+		max_deviation in any::<u32>(),
+	) {
+		let prices: Vec<_> = prices.into_iter().map(|(i, p)| price(i, p)).collect();
+		match aggregate(&prices, Permill::from_parts(max_deviation % 1_000_001)) {
+			Some((agreed, sources)) => {
+				prop_assert!(sources >= 1 && sources as usize <= prices.len());
+				prop_assert!(prices.iter().min().unwrap() <= &agreed);
+				prop_assert!(&agreed <= prices.iter().max().unwrap());
+			},
+			// Only an even number of prices can leave no price close enough to the median.
+			None => prop_assert!(prices.len() % 2 == 0),
+		}
+	}
+
+	#[test]
+	fn parse_price_rejects_negative_values(value in 1u64.., fraction in 0u32..1_000_000) {
+		let input = format!("-{}.{:06}", value, fraction);
+		prop_assert_eq!(parse_price(&input, 0), Err(ParseError::Negative));
+	}
+}
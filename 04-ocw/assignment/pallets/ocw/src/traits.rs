@@ -0,0 +1,22 @@
+//! Interfaces through which other pallets consume the prices recorded by `pallet_ocw`.
+
+use sp_arithmetic::per_things::Permill;
+
+/// Moving averages of the recorded prices, updated with every new price.
+pub trait AveragePrice {
+	/// Time-weighted average of the recorded prices of `symbol`, if any.
+	fn time_weighted_average(symbol: &[u8]) -> Option<(u64, Permill)>;
+
+	/// Exponential moving average of all prices of `symbol` recorded so far, if any.
+	fn exponential_moving_average(symbol: &[u8]) -> Option<(u64, Permill)>;
+}
+
+/// The latest recorded price, for consumers that must not act on an outdated or suspicious one.
+pub trait LatestPrice {
+	/// The most recent price of `symbol` and when it was recorded, in Unix milliseconds.
+	fn latest_timestamped_price(symbol: &[u8]) -> Option<((u64, Permill), u64)>;
+
+	/// The most recent price of `symbol`, unless it is older than the maximum age of the asset
+	/// or its circuit breaker tripped.
+	fn fresh_price(symbol: &[u8]) -> Option<(u64, Permill)>;
+}
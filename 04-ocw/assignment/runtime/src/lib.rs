@@ -33,7 +33,7 @@ pub use frame_support::{
 		constants::{BlockExecutionWeight, ExtrinsicBaseWeight, RocksDbWeight, WEIGHT_PER_SECOND},
 		IdentityFee, Weight,
 	},
-	StorageValue,
+	PalletId, StorageValue,
 };
 
 pub use pallet_balances::Call as BalancesCall;
@@ -48,6 +48,7 @@ use codec::Encode;
 /// Import the template pallet.
 pub use pallet_template;
 pub use pallet_ocw;
+use pallet_ocw::retry::RetryPolicy;
 
 /// An index to a block.
 pub type BlockNumber = u32;
@@ -88,6 +89,7 @@ pub mod opaque {
 		pub struct SessionKeys {
 			pub aura: Aura,
 			pub grandpa: Grandpa,
+			pub ocw: OcwDemo,
 		}
 	}
 }
@@ -104,7 +106,7 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	//   `spec_version`, and `authoring_version` are the same between Wasm and native.
 	// This value is set to 100 to notify Polkadot-JS App (https://polkadot.js.org/apps) to use
 	//   the compatible custom types.
-	spec_version: 100,
+	spec_version: 101,
 	impl_version: 1,
 	apis: RUNTIME_API_VERSIONS,
 	transaction_version: 1,
@@ -276,11 +278,71 @@ impl pallet_template::Config for Runtime {
 	type Event = Event;
 }
 
+parameter_types! {
+	pub const OcwPalletId: PalletId = PalletId(*b"py/ocwdm");
+	pub const ReporterReward: Balance = 1_000_000;
+	pub const MinReporterBond: Balance = 1_000_000_000;
+	pub ReportTolerance: Permill = Permill::from_percent(2);
+	pub DeviationSlash: Permill = Permill::from_percent(10);
+	pub const MaxOracles: u32 = 32;
+	pub const MaxPendingIndexing: u32 = 16;
+	pub const MaxMissedRounds: u32 = 10;
+	pub const SuspensionPeriod: BlockNumber = HOURS;
+	pub const CommitPeriod: BlockNumber = 2;
+	pub const RevealPeriod: BlockNumber = 2;
+	pub const MaxSymbolLength: u32 = 16;
+	pub const MaxFeedIdLength: u32 = 32;
+	pub const MaxFeedsPerAsset: u32 = 8;
+	pub MaxPriceDeviation: Permill = Permill::from_percent(5);
+	pub const MinPriceSources: u32 = 1;
+	pub const UnsignedInterval: BlockNumber = 1;
+	pub const MaxHistoryLength: u32 = 10;
+	pub const OffchainTimeBudget: u64 = MILLISECS_PER_BLOCK / 2;
+	// Under a block, so every price run fetches fresh prices and a round is never reported with
+	// the price of an earlier one. A cached price only stands in when the fetch fails.
+	pub const CacheTtl: u64 = MILLISECS_PER_BLOCK / 2;
+	pub const CacheMaxStale: u64 = MILLISECS_PER_BLOCK * 10;
+	pub HttpRetryPolicy: RetryPolicy = RetryPolicy {
+		max_attempts: 3,
+		backoff: 200,
+		retry_statuses: vec![429, 500, 502, 503, 504],
+		failure_threshold: 3,
+		cooldown: 5 * 60_000,
+	};
+	pub EmaSmoothing: Permill = Permill::from_percent(20);
+}
+
 /// For pallet-ocw
 impl pallet_ocw::Config for Runtime {
 	type AuthorityId = pallet_ocw::crypto::TestAuthId;
 	type Call = Call;
 	type Event = Event;
+	type Currency = Balances;
+	type PalletId = OcwPalletId;
+	type ReporterReward = ReporterReward;
+	type MinReporterBond = MinReporterBond;
+	type ReportTolerance = ReportTolerance;
+	type DeviationSlash = DeviationSlash;
+	type MaxOracles = MaxOracles;
+	type MaxPendingIndexing = MaxPendingIndexing;
+	type MaxMissedRounds = MaxMissedRounds;
+	type SuspensionPeriod = SuspensionPeriod;
+	type CommitPeriod = CommitPeriod;
+	type RevealPeriod = RevealPeriod;
+	type MaxSymbolLength = MaxSymbolLength;
+	type MaxFeedIdLength = MaxFeedIdLength;
+	type MaxFeedsPerAsset = MaxFeedsPerAsset;
+	type MaxPriceDeviation = MaxPriceDeviation;
+	type MinPriceSources = MinPriceSources;
+	type UnixTime = Timestamp;
+	type EmaSmoothing = EmaSmoothing;
+	type CacheTtl = CacheTtl;
+	type CacheMaxStale = CacheMaxStale;
+	type HttpRetryPolicy = HttpRetryPolicy;
+	type OffchainTimeBudget = OffchainTimeBudget;
+	type UnsignedInterval = UnsignedInterval;
+	type MaxHistoryLength = MaxHistoryLength;
+	type WeightInfo = pallet_ocw::weights::SubstrateWeight<Runtime>;
 }
 
 impl<LocalCall> frame_system::offchain::CreateSignedTransaction<LocalCall> for Runtime
@@ -350,7 +412,7 @@ construct_runtime!(
 		Sudo: pallet_sudo::{Pallet, Call, Config<T>, Storage, Event<T>},
 		// Include the custom logic from the pallet-template in the runtime.
 		TemplateModule: pallet_template::{Pallet, Call, Storage, Event<T>},
-		OcwDemo: pallet_ocw::{Pallet, Call, Storage, Event<T>, ValidateUnsigned},
+		OcwDemo: pallet_ocw::{Pallet, Call, Config<T>, Storage, Event<T>, ValidateUnsigned},
 	}
 );
 
@@ -497,6 +559,20 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl pallet_ocw_runtime_api::OcwApi<Block> for Runtime {
+		fn latest_price(asset: Vec<u8>) -> Option<(u64, Permill)> {
+			OcwDemo::latest_price(&asset)
+		}
+
+		fn price_history(asset: Vec<u8>) -> Vec<(u64, Permill)> {
+			OcwDemo::price_history(&asset)
+		}
+
+		fn twap(asset: Vec<u8>, window: u32) -> Option<(u64, Permill)> {
+			OcwDemo::twap(&asset, window)
+		}
+	}
+
 	impl pallet_transaction_payment_rpc_runtime_api::TransactionPaymentApi<Block, Balance> for Runtime {
 		fn query_info(
 			uxt: <Block as BlockT>::Extrinsic,
@@ -546,6 +622,7 @@ impl_runtime_apis! {
 
 			add_benchmark!(params, batches, frame_system, SystemBench::<Runtime>);
 			add_benchmark!(params, batches, pallet_balances, Balances);
+			add_benchmark!(params, batches, pallet_ocw, OcwDemo);
 			add_benchmark!(params, batches, pallet_timestamp, Timestamp);
 			add_benchmark!(params, batches, pallet_template, TemplateModule);
 